@@ -0,0 +1,65 @@
+use crate::resolve::Resolution;
+use crate::{read_vault, Command, FileData, VaultArgs};
+use clap::Args;
+use colored::Colorize;
+use std::fs;
+use std::io::Error;
+
+/// List every note linking to a note, with the lines that mention it
+#[derive(Args)]
+pub struct Backlinks {
+    #[command(flatten)]
+    vault: VaultArgs,
+    /// The note to find backlinks to, by name, path or alias
+    note: String,
+    /// Lines of context to show around each mention
+    #[arg(short = 'C', long, default_value_t = 1)]
+    context: usize,
+}
+
+impl Command for Backlinks {
+    fn execute(&self) -> Result<(), Error> {
+        let vault = read_vault(&self.vault)?;
+        let resolver = vault.resolver();
+        let target = vault.find(&self.note)?.path.clone();
+        // `vault.notes` is already ordered by date, like `list`
+        let linking: Vec<(&FileData, Vec<usize>)> = vault
+            .notes
+            .iter()
+            .filter(|n| n.path != target)
+            .filter_map(|n| {
+                let mut lines: Vec<usize> = n
+                    .links
+                    .iter()
+                    .filter(|l| l.is_internal())
+                    .filter(|l| {
+                        resolver.resolve(l, &n.path) == Resolution::Resolved(target.clone())
+                    })
+                    .map(|l| l.line)
+                    .collect();
+                lines.dedup();
+                Some((n, lines)).filter(|(_, l)| !l.is_empty())
+            })
+            .collect();
+        println!("{} notes link to {}", linking.len(), target.display());
+        for (n, (note, lines)) in linking.iter().enumerate() {
+            println!("[{: >2}] {}", n + 1, note);
+            let content = fs::read_to_string(self.vault.from.join(&note.path))?;
+            let text: Vec<&str> = content.lines().collect();
+            for line in lines {
+                let first = line.saturating_sub(self.context).max(1);
+                let last = (line + self.context).min(text.len());
+                for i in first..=last {
+                    let number = format!("{:>6}│", i);
+                    match (i == *line, text.get(i - 1)) {
+                        (true, Some(t)) => println!("{} {}", number.yellow(), t),
+                        (false, Some(t)) => println!("{} {}", number.dimmed(), t.dimmed()),
+                        (_, None) => {}
+                    }
+                }
+                println!("{:>7}", "┄".dimmed());
+            }
+        }
+        Ok(())
+    }
+}
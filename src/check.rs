@@ -0,0 +1,199 @@
+use crate::links::Link;
+use crate::resolve::Resolution;
+use crate::value::Value;
+use crate::{read_vault, Command, FileData, VaultArgs};
+use clap::{Args, ValueEnum};
+use serde::Serialize;
+use std::collections::HashSet;
+use std::fmt::{Display, Formatter};
+use std::io::Error;
+use std::path::PathBuf;
+
+/// Report broken links, missing embeds, orphan notes and missing parents.
+///
+/// Exits with status 1 when any problem is found, so it can run as a pre-commit hook,
+/// and with status 2 when the vault could not be checked at all.
+#[derive(Args)]
+pub struct Check {
+    #[command(flatten)]
+    vault: VaultArgs,
+    #[arg(long, value_enum, default_value_t = CheckFormat::Text)]
+    format: CheckFormat,
+    /// Do not report orphan notes
+    #[arg(long)]
+    allow_orphans: bool,
+}
+
+#[derive(ValueEnum, Clone, Copy)]
+enum CheckFormat {
+    /// One `path:line: kind: detail` line per problem
+    Text,
+    Json,
+}
+
+#[derive(Serialize, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+#[serde(rename_all = "kebab-case")]
+enum ProblemKind {
+    /// A link to a note that does not exist
+    BrokenLink,
+    /// A link that matches several notes
+    AmbiguousLink,
+    /// An `![[embed]]` of a file that does not exist
+    MissingEmbed,
+    /// A parent in the parents field that does not exist
+    MissingParent,
+    /// A note that links to nothing and that nothing links to
+    Orphan,
+}
+
+impl Display for ProblemKind {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}",
+            match self {
+                ProblemKind::BrokenLink => "broken-link",
+                ProblemKind::AmbiguousLink => "ambiguous-link",
+                ProblemKind::MissingEmbed => "missing-embed",
+                ProblemKind::MissingParent => "missing-parent",
+                ProblemKind::Orphan => "orphan",
+            }
+        )
+    }
+}
+
+#[derive(Serialize)]
+struct Problem {
+    kind: ProblemKind,
+    path: PathBuf,
+    line: Option<usize>,
+    detail: String,
+}
+
+impl Display for Problem {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self.line {
+            Some(l) => write!(f, "{}:{}: ", self.path.display(), l)?,
+            None => write!(f, "{}: ", self.path.display())?,
+        }
+        write!(f, "{}: {}", self.kind, self.detail)
+    }
+}
+
+impl Command for Check {
+    fn execute(&self) -> Result<(), Error> {
+        let vault = read_vault(&self.vault)?;
+        let resolver = vault.resolver();
+        let mut problems = Vec::new();
+        let mut linked: HashSet<PathBuf> = HashSet::new();
+        // Notes that link out only through a property, e.g. `up: "[[Index]]"`
+        let mut linking: HashSet<PathBuf> = HashSet::new();
+        for note in &vault.notes {
+            for link in note.links.iter().filter(|l| l.is_internal()) {
+                let kind = match resolver.resolve(link, &note.path) {
+                    Resolution::Resolved(p) => {
+                        if p != note.path {
+                            linked.insert(p);
+                        }
+                        continue;
+                    }
+                    Resolution::Ambiguous(paths) => {
+                        problems.push(Problem {
+                            kind: ProblemKind::AmbiguousLink,
+                            path: note.path.clone(),
+                            line: Some(link.line),
+                            detail: format!("{} matches {}", link, join(&paths)),
+                        });
+                        continue;
+                    }
+                    // Reported once, as a missing parent below
+                    Resolution::Unresolved if note.parents.contains(&link.target) => continue,
+                    Resolution::Unresolved if link.embed => ProblemKind::MissingEmbed,
+                    Resolution::Unresolved => ProblemKind::BrokenLink,
+                };
+                problems.push(Problem {
+                    kind,
+                    path: note.path.clone(),
+                    line: Some(link.line),
+                    detail: link.to_string(),
+                });
+            }
+            for parent in &note.parents {
+                if resolver.resolve(&Link::wiki(parent), &note.path) == Resolution::Unresolved {
+                    problems.push(Problem {
+                        kind: ProblemKind::MissingParent,
+                        path: note.path.clone(),
+                        line: None,
+                        detail: parent.clone(),
+                    });
+                }
+            }
+            for link in property_links(note) {
+                linking.insert(note.path.clone());
+                if let Resolution::Resolved(p) = resolver.resolve(&link, &note.path) {
+                    if p != note.path {
+                        linked.insert(p);
+                    }
+                }
+            }
+        }
+        if !self.allow_orphans {
+            for note in &vault.notes {
+                if !linked.contains(&note.path)
+                    && !linking.contains(&note.path)
+                    && !note.links.iter().any(|l| l.is_internal())
+                {
+                    problems.push(Problem {
+                        kind: ProblemKind::Orphan,
+                        path: note.path.clone(),
+                        line: None,
+                        detail: "no links to or from this note".into(),
+                    });
+                }
+            }
+        }
+        problems.sort_by(|a, b| (&a.path, a.line, a.kind).cmp(&(&b.path, b.line, b.kind)));
+        match self.format {
+            CheckFormat::Text => {
+                for p in &problems {
+                    println!("{}", p);
+                }
+            }
+            CheckFormat::Json => println!(
+                "{}",
+                serde_json::to_string_pretty(&problems).map_err(Error::other)?
+            ),
+        }
+        if !problems.is_empty() {
+            eprintln!("{} problems found", problems.len());
+            std::process::exit(1);
+        }
+        Ok(())
+    }
+}
+
+/// The links in the properties of a note, which are not part of its body links.
+fn property_links(note: &FileData) -> Vec<Link> {
+    fn collect(value: &Value, links: &mut Vec<Link>) {
+        match value {
+            Value::Link(link) => links.push(link.clone()),
+            Value::List(items) => items.iter().for_each(|v| collect(v, links)),
+            _ => {}
+        }
+    }
+    let mut links = Vec::new();
+    for (key, value) in &note.metadata {
+        if key != "links" {
+            collect(value, &mut links);
+        }
+    }
+    links
+}
+
+fn join(paths: &[PathBuf]) -> String {
+    paths
+        .iter()
+        .map(|p| p.display().to_string())
+        .collect::<Vec<String>>()
+        .join(", ")
+}
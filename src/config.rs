@@ -0,0 +1,116 @@
+use crate::date::DateSource;
+use serde::{Deserialize, Deserializer};
+use std::fs;
+use std::io::{Error, ErrorKind};
+use std::path::{Path, PathBuf};
+
+/// Name of the config file looked up in the vault root.
+pub const CONFIG_FILE: &str = "karat.toml";
+
+/// Vault configuration read from `karat.toml`.
+///
+/// ```toml
+/// separator = "::"
+/// value-split = ","
+/// date-sources = ["field", "filename", "git", "mtime"]
+///
+/// [fields]
+/// date = ["tarikh", "date"]
+/// parents = "idx-naik"
+/// tags = ["hashtag", "keywords"]
+/// aliases = "aliases"
+/// ```
+#[derive(Deserialize, Debug, Clone)]
+#[serde(default, rename_all = "kebab-case", deny_unknown_fields)]
+pub struct Config {
+    /// Separates the items of a list value, e.g. `idx-naik:: A :: B`
+    pub separator: String,
+    /// Splits the value of an inline field into several values, e.g. `key:: a, b`
+    pub value_split: String,
+    /// Where to look for the date of a note, in order
+    pub date_sources: Vec<DateSource>,
+    pub fields: FieldMap,
+}
+
+/// Source keys for each logical field of a note.
+///
+/// The date is taken from the first key that is present.
+/// Parents, tags and aliases are gathered from every key listed.
+#[derive(Deserialize, Debug, Clone)]
+#[serde(default, deny_unknown_fields)]
+pub struct FieldMap {
+    #[serde(deserialize_with = "one_or_many")]
+    pub date: Vec<String>,
+    #[serde(deserialize_with = "one_or_many")]
+    pub parents: Vec<String>,
+    #[serde(deserialize_with = "one_or_many")]
+    pub tags: Vec<String>,
+    /// Other names a note can be linked by
+    #[serde(deserialize_with = "one_or_many")]
+    pub aliases: Vec<String>,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            separator: "::".into(),
+            value_split: ",".into(),
+            date_sources: vec![
+                DateSource::Field,
+                DateSource::Filename,
+                DateSource::Git,
+                DateSource::Mtime,
+            ],
+            fields: Default::default(),
+        }
+    }
+}
+
+impl Default for FieldMap {
+    fn default() -> Self {
+        Self {
+            date: vec!["tarikh".into()],
+            parents: vec!["idx-naik".into()],
+            tags: vec!["hashtag".into(), "tags".into()],
+            aliases: vec!["aliases".into(), "alias".into()],
+        }
+    }
+}
+
+impl Config {
+    /// Load the config given on the command line, or `karat.toml` in the vault root.
+    ///
+    /// A missing `karat.toml` in the vault root falls back to the defaults, but a
+    /// missing `--config` file is an error.
+    pub fn load(vault: &Path, explicit: Option<&Path>) -> Result<Self, Error> {
+        let path = match explicit {
+            Some(p) => p.to_path_buf(),
+            None => {
+                let p: PathBuf = vault.join(CONFIG_FILE);
+                if !p.is_file() {
+                    return Ok(Self::default());
+                }
+                p
+            }
+        };
+        let content = fs::read_to_string(&path)?;
+        toml::from_str(&content)
+            .map_err(|e| Error::new(ErrorKind::InvalidData, format!("{}: {}", path.display(), e)))
+    }
+}
+
+fn one_or_many<'de, D>(deserializer: D) -> Result<Vec<String>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum OneOrMany {
+        One(String),
+        Many(Vec<String>),
+    }
+    Ok(match OneOrMany::deserialize(deserializer)? {
+        OneOrMany::One(s) => vec![s],
+        OneOrMany::Many(v) => v,
+    })
+}
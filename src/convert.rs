@@ -0,0 +1,61 @@
+use crate::document::DocumentAST;
+use crate::embed::{self, Transclusion};
+use crate::frontmatter;
+use crate::pandoc::Lowering;
+use crate::{read_vault, Command, VaultArgs};
+use clap::{Args, ValueEnum};
+use std::fs;
+use std::io::{Error, Write};
+use std::path::PathBuf;
+
+/// Write a note in another format, e.g. for `pandoc -f json -o note.docx`
+#[derive(Args)]
+pub struct Convert {
+    #[command(flatten)]
+    vault: VaultArgs,
+    /// The note to convert, by name, path or alias
+    note: String,
+    /// Format to write the note in
+    #[arg(long, value_enum)]
+    to: ConvertTo,
+    /// Write the metadata too, like `pandoc -s`, in formats that can leave it out
+    #[arg(short, long)]
+    standalone: bool,
+    /// How many embeds deep to expand, 0 to keep `![[embeds]]` as links
+    #[arg(long, default_value_t = embed::MAX_DEPTH)]
+    embed_depth: usize,
+    /// File to write to instead of stdout
+    #[arg(short, long)]
+    output: Option<PathBuf>,
+}
+
+#[derive(ValueEnum, Clone, Copy)]
+pub enum ConvertTo {
+    /// Pandoc's JSON AST, as read by `pandoc -f json`
+    PandocJson,
+    /// Pandoc's native format, to diff against `pandoc -t native`
+    Native,
+}
+
+impl Command for Convert {
+    fn execute(&self) -> Result<(), Error> {
+        let vault = read_vault(&self.vault)?;
+        let note = vault.find(&self.note)?;
+        let content = fs::read_to_string(self.vault.from.join(&note.path))?;
+        let (_, body) = frontmatter::split(&content);
+        let mut doc = DocumentAST::parse(body, note.metadata.clone());
+        doc.content = Transclusion::new(&vault, &self.vault.from, self.embed_depth)
+            .expand(doc.content, &note.path);
+        let pandoc = Lowering::new(&vault, note).lower(doc);
+        let text = match self.to {
+            ConvertTo::PandocJson => {
+                serde_json::to_string(&pandoc.to_json()).map_err(Error::other)?
+            }
+            ConvertTo::Native => pandoc.to_native(self.standalone),
+        };
+        match &self.output {
+            Some(path) => fs::write(path, text + "\n"),
+            None => writeln!(std::io::stdout().lock(), "{}", text),
+        }
+    }
+}
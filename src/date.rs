@@ -0,0 +1,332 @@
+use chrono::{NaiveDate, NaiveDateTime, Weekday};
+use lazy_static::lazy_static;
+use regex::Regex;
+use serde::Deserialize;
+use std::cell::OnceCell;
+use std::collections::HashMap;
+use std::fmt::{Display, Formatter};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command as Process;
+
+lazy_static! {
+    static ref ISO_WEEK_RE: Regex = Regex::new(
+        r"(?x)
+        ^(?P<year>\d{4})
+        -?W(?P<week>\d{2})      # the week
+        (?:-?(?P<day>[1-7]))?$  # the weekday, Monday if left out
+        ",
+    )
+    .expect("Regex run error");
+    static ref NUMERIC_RE: Regex = Regex::new(
+        r"(?x)
+        ^(?P<day>\d{1,2})
+        [/.-]
+        (?P<month>\d{1,2})
+        [/.-]
+        (?P<year>\d{4})$
+        ",
+    )
+    .expect("Regex run error");
+    static ref DAY_MONTH_RE: Regex = Regex::new(
+        r"(?x)
+        ^(?P<day>\d{1,2})(?:st|nd|rd|th|hb)?  # `1st March`, `1hb Mac`
+        \s+(?P<month>\p{L}+)\.?,?
+        \s+(?P<year>\d{4})$
+        ",
+    )
+    .expect("Regex run error");
+    static ref MONTH_DAY_RE: Regex = Regex::new(
+        r"(?x)
+        ^(?P<month>\p{L}+)\.?
+        \s+(?P<day>\d{1,2})(?:st|nd|rd|th)?,?
+        \s+(?P<year>\d{4})$
+        ",
+    )
+    .expect("Regex run error");
+    static ref WEEKDAY_PREFIX_RE: Regex =
+        Regex::new(r"^\p{L}+,\s*(?P<rest>.+)$").expect("Regex run error");
+}
+
+/// English and Malay month names, full and abbreviated.
+const MONTHS: [&[&str]; 12] = [
+    &["january", "januari", "jan"],
+    &["february", "februari", "feb"],
+    &["march", "mac", "mar"],
+    &["april", "apr"],
+    &["may", "mei"],
+    &["june", "jun"],
+    &["july", "julai", "jul"],
+    &["august", "ogos", "aug", "ogo"],
+    &["september", "sept", "sep"],
+    &["october", "oktober", "oct", "okt"],
+    &["november", "nov"],
+    &["december", "disember", "dec", "dis"],
+];
+
+fn month_number(name: &str) -> Option<u32> {
+    let name = name.to_lowercase();
+    MONTHS
+        .iter()
+        .position(|names| names.contains(&name.as_str()))
+        .map(|i| i as u32 + 1)
+}
+
+/// Parse a date written in any of the formats found in our notes:
+///
+/// - ISO dates, `2024-03-01`
+/// - ISO week dates, `2024-W09` or `2024-W09-5`
+/// - `DD/MM/YYYY`, also with `-` or `.` between the numbers
+/// - Month names in English or Malay, `1 March 2024`, `1hb Mac 2024`,
+///   `March 1, 2024`, optionally after a weekday such as `Jumaat, `
+pub fn parse_date(value: &str) -> Option<NaiveDate> {
+    let value = value.trim();
+    if let Ok(d) = NaiveDate::parse_from_str(value, "%Y-%m-%d") {
+        return Some(d);
+    }
+    if let Some(c) = ISO_WEEK_RE.captures(value) {
+        let day = match c.name("day").map(|d| d.as_str()) {
+            Some("2") => Weekday::Tue,
+            Some("3") => Weekday::Wed,
+            Some("4") => Weekday::Thu,
+            Some("5") => Weekday::Fri,
+            Some("6") => Weekday::Sat,
+            Some("7") => Weekday::Sun,
+            _ => Weekday::Mon,
+        };
+        return NaiveDate::from_isoywd_opt(c["year"].parse().ok()?, c["week"].parse().ok()?, day);
+    }
+    if let Some(c) = NUMERIC_RE.captures(value) {
+        return NaiveDate::from_ymd_opt(
+            c["year"].parse().ok()?,
+            c["month"].parse().ok()?,
+            c["day"].parse().ok()?,
+        );
+    }
+    if let Some(c) = DAY_MONTH_RE
+        .captures(value)
+        .or_else(|| MONTH_DAY_RE.captures(value))
+    {
+        return NaiveDate::from_ymd_opt(
+            c["year"].parse().ok()?,
+            month_number(&c["month"])?,
+            c["day"].parse().ok()?,
+        );
+    }
+    if let Some(c) = WEEKDAY_PREFIX_RE.captures(value) {
+        return parse_date(&c["rest"]);
+    }
+    None
+}
+
+/// Parse a date with a time of day, with or without a timezone.
+///
+/// The wall time is kept as written, so the date does not move across midnight
+/// when the timezone differs from ours.
+pub fn parse_datetime(value: &str) -> Option<NaiveDateTime> {
+    let value = value.trim();
+    if let Ok(dt) = chrono::DateTime::parse_from_rfc3339(value) {
+        return Some(dt.naive_local());
+    }
+    if let Some(dt) = [
+        "%Y-%m-%d %H:%M:%S %z",
+        "%Y-%m-%d %H:%M %z",
+        "%Y-%m-%dT%H:%M%z",
+    ]
+    .iter()
+    .find_map(|f| chrono::DateTime::parse_from_str(value, f).ok())
+    {
+        return Some(dt.naive_local());
+    }
+    [
+        "%Y-%m-%dT%H:%M:%S%.f",
+        "%Y-%m-%dT%H:%M",
+        "%Y-%m-%d %H:%M:%S",
+        "%Y-%m-%d %H:%M",
+        "%d/%m/%Y %H:%M",
+    ]
+    .iter()
+    .find_map(|f| NaiveDateTime::parse_from_str(value, f).ok())
+}
+
+lazy_static! {
+    static ref FILENAME_DATE_RE: Regex =
+        Regex::new(r"\d{4}-\d{2}-\d{2}|\d{4}-?W\d{2}(?:-?[1-7])?").expect("Regex run error");
+}
+
+/// Where the date of a note came from, tried in the order given in the config.
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum DateSource {
+    /// The date field of the note, e.g. `tarikh::`
+    Field,
+    /// A date in the file name, e.g. daily notes named `2024-03-01.md`
+    Filename,
+    /// The first commit that added the note to the local git repository
+    Git,
+    /// The modification time on the filesystem
+    Mtime,
+}
+
+impl Display for DateSource {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}",
+            match self {
+                DateSource::Field => "field",
+                DateSource::Filename => "filename",
+                DateSource::Git => "git",
+                DateSource::Mtime => "mtime",
+            }
+        )
+    }
+}
+
+/// Finds the date of a note by going through the configured [DateSource]s.
+pub struct DateResolver<'a> {
+    root: &'a Path,
+    sources: &'a [DateSource],
+    /// Filled on first use, so vaults where every note has a date never run git
+    git: OnceCell<HashMap<PathBuf, NaiveDate>>,
+}
+
+impl<'a> DateResolver<'a> {
+    pub fn new(root: &'a Path, sources: &'a [DateSource]) -> Self {
+        Self {
+            root,
+            sources,
+            git: OnceCell::new(),
+        }
+    }
+
+    /// `path` is relative to the vault root, `field` is the date read from the note.
+    pub fn resolve(
+        &self,
+        path: &Path,
+        field: Option<NaiveDate>,
+    ) -> Option<(NaiveDate, DateSource)> {
+        self.sources.iter().find_map(|&source| {
+            let date = match source {
+                DateSource::Field => field,
+                DateSource::Filename => from_filename(path),
+                DateSource::Git => self
+                    .git
+                    .get_or_init(|| git_dates(self.root))
+                    .get(path)
+                    .copied(),
+                DateSource::Mtime => from_mtime(&self.root.join(path)),
+            };
+            date.map(|d| (d, source))
+        })
+    }
+}
+
+fn from_filename(path: &Path) -> Option<NaiveDate> {
+    let stem = path.file_stem()?.to_str()?;
+    parse_date(stem).or_else(|| {
+        FILENAME_DATE_RE
+            .find_iter(stem)
+            .find_map(|m| parse_date(m.as_str()))
+    })
+}
+
+fn from_mtime(path: &Path) -> Option<NaiveDate> {
+    let modified = fs::metadata(path).ok()?.modified().ok()?;
+    Some(chrono::DateTime::<chrono::Local>::from(modified).date_naive())
+}
+
+/// Date of the commit that first added each file, keyed by path relative to `root`.
+///
+/// Returns an empty map when `root` is not inside a git repository or git is missing.
+fn git_dates(root: &Path) -> HashMap<PathBuf, NaiveDate> {
+    let mut dates = HashMap::new();
+    let output = Process::new("git")
+        .arg("-C")
+        .arg(root)
+        .args([
+            "-c",
+            "core.quotePath=false",
+            "log",
+            "--diff-filter=A",
+            "--name-only",
+            "--relative",
+            "--format=date:%as",
+        ])
+        .output();
+    let Ok(output) = output.map(|o| o.stdout) else {
+        return dates;
+    };
+    let mut current = None;
+    // Newest commits come first, so later lines overwrite with older dates
+    for line in String::from_utf8_lossy(&output).lines() {
+        if let Some(d) = line.strip_prefix("date:") {
+            current = NaiveDate::parse_from_str(d, "%Y-%m-%d").ok();
+        } else if let (Some(d), false) = (current, line.is_empty()) {
+            dates.insert(PathBuf::from(line), d);
+        }
+    }
+    dates
+}
+
+/// Parse a date given on the command line, which may also be `today`,
+/// `tomorrow` or `yesterday`.
+pub fn parse_date_arg(value: &str) -> Result<NaiveDate, String> {
+    let today = chrono::Local::now().date_naive();
+    match value.trim().to_lowercase().as_str() {
+        "today" => Ok(today),
+        "tomorrow" => Ok(today + chrono::Duration::days(1)),
+        "yesterday" => Ok(today - chrono::Duration::days(1)),
+        _ => parse_date(value).ok_or(format!("`{}` is not a date", value)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date(y: i32, m: u32, d: u32) -> Option<NaiveDate> {
+        NaiveDate::from_ymd_opt(y, m, d)
+    }
+
+    #[test]
+    fn date_formats() {
+        assert_eq!(parse_date("2024-03-01"), date(2024, 3, 1));
+        assert_eq!(parse_date("01/03/2024"), date(2024, 3, 1));
+        assert_eq!(parse_date("1.3.2024"), date(2024, 3, 1));
+        assert_eq!(parse_date("1 March 2024"), date(2024, 3, 1));
+        assert_eq!(parse_date("1hb Mac 2024"), date(2024, 3, 1));
+        assert_eq!(parse_date("March 1st, 2024"), date(2024, 3, 1));
+        assert_eq!(parse_date("Jumaat, 1 Mac 2024"), date(2024, 3, 1));
+    }
+
+    #[test]
+    fn iso_week_dates() {
+        assert_eq!(parse_date("2024-W09-5"), date(2024, 3, 1));
+        assert_eq!(parse_date("2024W095"), date(2024, 3, 1));
+        assert_eq!(parse_date("2024-W09"), date(2024, 2, 26));
+        assert_eq!(parse_date("2024-W54"), None);
+    }
+
+    #[test]
+    fn bad_dates_do_not_panic() {
+        for value in [
+            "",
+            "31/02/2024",
+            "1 Smarch 2024",
+            "2024-13-01",
+            "tarikh",
+            "١٢",
+        ] {
+            assert_eq!(parse_date(value), None, "{}", value);
+        }
+    }
+
+    #[test]
+    fn datetimes_keep_their_wall_time() {
+        let expected = date(2024, 3, 1).and_then(|d| d.and_hms_opt(23, 30, 0));
+        assert_eq!(parse_datetime("2024-03-01T23:30:00+08:00"), expected);
+        assert_eq!(parse_datetime("2024-03-01 23:30"), expected);
+        assert_eq!(parse_datetime("01/03/2024 23:30"), expected);
+    }
+}
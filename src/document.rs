@@ -0,0 +1,1100 @@
+use crate::links::Link;
+use crate::markdown;
+use crate::value::Metadata;
+use lazy_static::lazy_static;
+use pulldown_cmark::{CodeBlockKind, Event, LinkType, Options, Parser, Tag};
+use regex::Regex;
+use std::collections::HashMap;
+use std::fmt::{Display, Formatter};
+use std::iter::Peekable;
+use std::ops::Range;
+use std::vec::IntoIter;
+
+lazy_static! {
+    /// Obsidian syntax inside a run of text, in order of precedence
+    static ref OBSIDIAN_RE: Regex = Regex::new(
+        r"(?x)
+        (?P<embed>!)?\[\[(?P<link>[^\[\]]+?)\]\]           # [[wikilink]] and ![[embed]]
+        | ==(?P<mark>[^=\s](?:[^=]*[^=\s])?)==              # ==highlight==
+        | %%(?P<comment>.*?)%%                              # %%comment%%
+        | \$\$(?P<display>[^$]+?)\$\$                       # $$display math$$
+        | \$(?P<math>[^$\s](?:[^$]*[^$\s])?)\$              # $inline math$
+        | \[(?P<key>[^\[\]():]+)::(?P<value>[^\[\]]*)\]     # [key:: value]
+        | \((?P<pkey>[^\[\]():]+)::(?P<pvalue>[^()]*)\)     # (key:: value)
+        | (?P<pre>^|\s)(?P<tag>\#[\p{L}\p{M}\p{N}_/-]+)     # #tag
+        | (?P<bpre>^|\s)\^(?P<block>[A-Za-z0-9-]+)$         # ^block-id at the end
+        ",
+    )
+    .expect("Regex run error");
+    static ref CALLOUT_RE: Regex =
+        Regex::new(r"^\[!(?P<kind>[^\]]+)\](?P<fold>[+-])?(?P<rest>.*)$").expect("Regex run error");
+    static ref TASK_RE: Regex = Regex::new(r"^\[(?P<status>.)\]$").expect("Regex run error");
+}
+
+/// A note as a tree of blocks and inlines, modelled on pandoc's
+/// [Text.Pandoc.Definition](https://hackage.haskell.org/package/pandoc-types/docs/Text-Pandoc-Definition.html).
+///
+/// Only the elements markdown can produce are modelled. Obsidian syntax that
+/// pandoc has no element for, such as wikilinks, tags and callouts, gets
+/// elements of its own, and each exporter decides how to write them.
+#[derive(Debug, Clone, Default)]
+pub struct DocumentAST {
+    pub meta: Metadata,
+    pub content: Vec<BlockElement>,
+}
+
+/// The struct resembling HTML tags, i.e. `<div>children</div>`
+///
+/// Pandoc Native representation:
+/// ```haskell
+/// Elem
+///     (iden, classes, attrs)
+///     [ children ]
+/// ```
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct HTMLItem<Child> {
+    pub ident: String,
+    pub classes: Vec<String>,
+    pub attrs: Vec<(String, String)>,
+    pub children: Child,
+}
+
+impl<Child> HTMLItem<Child> {
+    pub fn new(children: Child) -> Self {
+        Self {
+            ident: String::new(),
+            classes: Vec::new(),
+            attrs: Vec::new(),
+            children,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum MathItem {
+    Display(String),
+    Inline(String),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Alignment {
+    Left,
+    Right,
+    Center,
+    Default,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct TableTag {
+    pub aligns: Vec<Alignment>,
+    /// Cells of the header row
+    pub head: Vec<Vec<InlineElement>>,
+    pub rows: Vec<Vec<Vec<InlineElement>>>,
+}
+
+/// The delimiter after the numbers of an ordered list
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ListDelim {
+    /// `1.`
+    Period,
+    /// `1)`
+    OneParen,
+}
+
+/// An Obsidian callout, a block quote starting with `> [!kind] title`
+#[derive(Debug, Clone, PartialEq)]
+pub struct Callout {
+    /// The callout type in lowercase, e.g. `note` or `warning`
+    pub kind: String,
+    /// `+` or `-` when the callout is foldable
+    pub fold: Option<char>,
+    pub title: Vec<InlineElement>,
+    pub content: Vec<BlockElement>,
+}
+
+impl Callout {
+    /// The title as Obsidian shows it, which is the type, capitalised,
+    /// when the callout has none.
+    pub fn shown_title(&self) -> Vec<InlineElement> {
+        if !self.title.is_empty() {
+            return self.title.clone();
+        }
+        let mut kind = self.kind.chars();
+        let title: String = kind
+            .next()
+            .into_iter()
+            .flat_map(char::to_uppercase)
+            .chain(kind)
+            .collect();
+        words(&title)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum InlineElement {
+    Str(String),
+    Emph(Vec<InlineElement>),
+    Strong(Vec<InlineElement>),
+    Strikeout(Vec<InlineElement>),
+    Code(HTMLItem<String>),
+    Space,
+    SoftBreak,
+    LineBreak,
+    Math(MathItem),
+    /// (Format, Text)
+    RawInline(String, String),
+    /// A markdown link, with its (URL, title)
+    Link(HTMLItem<Vec<InlineElement>>, (String, String)),
+    Image(HTMLItem<Vec<InlineElement>>, (String, String)),
+    /// A footnote, with the blocks of its definition
+    Note(Vec<BlockElement>),
+    /// Inlines with attributes, written by exporters for Obsidian syntax
+    Span(HTMLItem<Vec<InlineElement>>),
+    /// `[[Note#Heading|alias]]`
+    WikiLink(Link),
+    /// `![[Note]]`, the whole note or part of it shown in place
+    Embed(Link),
+    /// `#tag`, with the `#`
+    Tag(String),
+    /// `==highlighted==`
+    Highlight(Vec<InlineElement>),
+    /// `%%hidden%%`
+    Comment(String),
+    /// `^block-id` at the end of a block, the target of `[[Note#^block-id]]`
+    BlockId(String),
+    /// `[key:: value]` or `(key:: value)`
+    Field(String, String),
+    /// The status of a task list item, e.g. `' '`, `'x'` or `'/'`
+    Task(char),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum BlockElement {
+    Plain(Vec<InlineElement>),
+    Para(Vec<InlineElement>),
+    CodeBlock(HTMLItem<String>),
+    /// (Format, Text)
+    RawBlock(String, String),
+    BlockQuote(Vec<BlockElement>),
+    /// ((start, delimiter), \[Block\])
+    OrderedList((u64, ListDelim), Vec<Vec<BlockElement>>),
+    BulletList(Vec<Vec<BlockElement>>),
+    Header(u8, HTMLItem<Vec<InlineElement>>),
+    HorizontalRule,
+    Table(TableTag),
+    Callout(Callout),
+    /// Blocks with attributes, written by exporters for Obsidian syntax
+    Div(HTMLItem<Vec<BlockElement>>),
+    /// A paragraph made only of a `%% comment %%`
+    Comment(String),
+}
+
+type Spanned<'a> = (Event<'a>, Range<usize>);
+
+/// Turns pulldown-cmark events into blocks and inlines.
+///
+/// Every element reads up to and including its own `End` event. Events that
+/// do not fit where they appear are skipped, so no input makes it panic.
+struct Builder<'a> {
+    source: &'a str,
+    events: Peekable<IntoIter<Spanned<'a>>>,
+    footnotes: &'a HashMap<String, Vec<BlockElement>>,
+    /// Header identifiers given so far, to number the repeated ones
+    idents: HashMap<String, usize>,
+}
+
+/// Pending text of an inline run, so that text split into several events
+/// (pulldown-cmark splits at every `[`) is scanned for Obsidian syntax as a whole.
+#[derive(Default)]
+struct InlineBuf {
+    items: Vec<InlineElement>,
+    text: String,
+}
+
+impl InlineBuf {
+    fn push(&mut self, inline: InlineElement) {
+        self.flush();
+        self.items.push(inline);
+    }
+
+    fn flush(&mut self) {
+        if !self.text.is_empty() {
+            let text = std::mem::take(&mut self.text);
+            self.items.extend(obsidian_inlines(&text));
+        }
+    }
+
+    fn finish(mut self) -> Vec<InlineElement> {
+        self.flush();
+        tidy(highlights(self.items))
+    }
+}
+
+fn is_inline(event: &Event) -> bool {
+    matches!(
+        event,
+        Event::Text(_)
+            | Event::Code(_)
+            | Event::SoftBreak
+            | Event::HardBreak
+            | Event::FootnoteReference(_)
+            | Event::TaskListMarker(_)
+            | Event::Start(
+                Tag::Emphasis | Tag::Strong | Tag::Strikethrough | Tag::Link(..) | Tag::Image(..)
+            )
+    )
+}
+
+impl<'a> Builder<'a> {
+    fn new(
+        source: &'a str,
+        events: Vec<Spanned<'a>>,
+        footnotes: &'a HashMap<String, Vec<BlockElement>>,
+    ) -> Self {
+        Self {
+            source,
+            events: events.into_iter().peekable(),
+            footnotes,
+            idents: HashMap::new(),
+        }
+    }
+
+    /// Skip the rest of the element whose `Start` was just read.
+    fn skip(&mut self) {
+        let mut depth = 1;
+        for (event, _) in self.events.by_ref() {
+            match event {
+                Event::Start(_) => depth += 1,
+                Event::End(_) => depth -= 1,
+                _ => {}
+            }
+            if depth == 0 {
+                break;
+            }
+        }
+    }
+
+    fn blocks(&mut self) -> Vec<BlockElement> {
+        let mut blocks = Vec::new();
+        while let Some((event, _)) = self.events.peek() {
+            // Tight list items hold their text without a paragraph around it
+            if is_inline(event) {
+                let inlines = self.inline_run();
+                if !inlines.is_empty() {
+                    blocks.push(BlockElement::Plain(inlines));
+                }
+                continue;
+            }
+            let Some((event, range)) = self.events.next() else {
+                break;
+            };
+            match event {
+                Event::End(_) => break,
+                Event::Start(tag) => blocks.extend(self.block(tag, range)),
+                Event::Rule => blocks.push(BlockElement::HorizontalRule),
+                // An HTML block comes one line at a time
+                Event::Html(html) => match blocks.last_mut() {
+                    Some(BlockElement::RawBlock(format, text)) if format == "html" => {
+                        text.push_str(&html)
+                    }
+                    _ => blocks.push(BlockElement::RawBlock("html".into(), html.to_string())),
+                },
+                _ => {}
+            }
+        }
+        blocks
+    }
+
+    fn block(&mut self, tag: Tag<'a>, range: Range<usize>) -> Option<BlockElement> {
+        match tag {
+            Tag::Paragraph => {
+                let raw = self.source.get(range).unwrap_or_default().trim();
+                let wrapped = |mark: &str| {
+                    raw.strip_prefix(mark)
+                        .and_then(|r| r.strip_suffix(mark))
+                        .filter(|_| raw.len() >= 2 * mark.len())
+                        .map(|inner| inner.trim().to_string())
+                };
+                if let Some(math) = wrapped("$$") {
+                    self.skip();
+                    return Some(BlockElement::Para(vec![InlineElement::Math(
+                        MathItem::Display(math),
+                    )]));
+                }
+                if let Some(comment) = wrapped("%%").filter(|c| !c.contains("%%")) {
+                    self.skip();
+                    return Some(BlockElement::Comment(comment));
+                }
+                let inlines = self.inlines();
+                (!inlines.is_empty()).then_some(BlockElement::Para(inlines))
+            }
+            Tag::Heading(level, ident, classes) => {
+                let children = self.inlines();
+                let ident = match ident {
+                    Some(i) => i.to_string(),
+                    None => self.auto_ident(&stringify(&children)),
+                };
+                Some(BlockElement::Header(
+                    level as u8,
+                    HTMLItem {
+                        ident,
+                        classes: classes.iter().map(|c| c.to_string()).collect(),
+                        attrs: Vec::new(),
+                        children,
+                    },
+                ))
+            }
+            Tag::BlockQuote => Some(callout(self.blocks())),
+            Tag::CodeBlock(kind) => {
+                let mut code = String::new();
+                for (event, _) in self.events.by_ref() {
+                    match event {
+                        Event::Text(t) => code.push_str(&t),
+                        Event::End(_) => break,
+                        _ => {}
+                    }
+                }
+                let classes = match kind {
+                    CodeBlockKind::Fenced(info) => info
+                        .split_whitespace()
+                        .next()
+                        .map(|lang| vec![lang.to_string()])
+                        .unwrap_or_default(),
+                    CodeBlockKind::Indented => Vec::new(),
+                };
+                Some(BlockElement::CodeBlock(HTMLItem {
+                    classes,
+                    ..HTMLItem::new(code.strip_suffix('\n').unwrap_or(&code).to_string())
+                }))
+            }
+            Tag::List(start) => {
+                let mut items = Vec::new();
+                while let Some((event, _)) = self.events.next() {
+                    match event {
+                        Event::Start(Tag::Item) => items.push(task_item(self.blocks())),
+                        Event::Start(_) => self.skip(),
+                        Event::End(_) => break,
+                        _ => {}
+                    }
+                }
+                Some(match start {
+                    Some(n) => {
+                        let marker = self.source[range.start..]
+                            .trim_start()
+                            .trim_start_matches(|c: char| c.is_ascii_digit());
+                        let delim = match marker.starts_with(')') {
+                            true => ListDelim::OneParen,
+                            false => ListDelim::Period,
+                        };
+                        BlockElement::OrderedList((n, delim), items)
+                    }
+                    None => BlockElement::BulletList(items),
+                })
+            }
+            Tag::Table(aligns) => {
+                let aligns = aligns
+                    .iter()
+                    .map(|a| match a {
+                        pulldown_cmark::Alignment::Left => Alignment::Left,
+                        pulldown_cmark::Alignment::Right => Alignment::Right,
+                        pulldown_cmark::Alignment::Center => Alignment::Center,
+                        pulldown_cmark::Alignment::None => Alignment::Default,
+                    })
+                    .collect();
+                let mut table = TableTag {
+                    aligns,
+                    head: Vec::new(),
+                    rows: Vec::new(),
+                };
+                while let Some((event, _)) = self.events.next() {
+                    match event {
+                        Event::Start(Tag::TableHead) => table.head = self.cells(),
+                        Event::Start(Tag::TableRow) => table.rows.push(self.cells()),
+                        Event::Start(_) => self.skip(),
+                        Event::End(_) => break,
+                        _ => {}
+                    }
+                }
+                Some(BlockElement::Table(table))
+            }
+            // An item outside a list, or a block that holds other blocks
+            Tag::Item | Tag::FootnoteDefinition(_) => {
+                self.blocks();
+                None
+            }
+            _ => {
+                self.skip();
+                None
+            }
+        }
+    }
+
+    fn cells(&mut self) -> Vec<Vec<InlineElement>> {
+        let mut cells = Vec::new();
+        while let Some((event, _)) = self.events.next() {
+            match event {
+                Event::Start(Tag::TableCell) => cells.push(self.inlines()),
+                Event::Start(_) => self.skip(),
+                Event::End(_) => break,
+                _ => {}
+            }
+        }
+        cells
+    }
+
+    /// Inlines up to the end of the element whose `Start` was just read.
+    fn inlines(&mut self) -> Vec<InlineElement> {
+        let mut buf = InlineBuf::default();
+        while let Some((event, _)) = self.events.next() {
+            match event {
+                Event::End(_) => break,
+                event => self.inline(event, &mut buf),
+            }
+        }
+        buf.finish()
+    }
+
+    /// Inlines up to the next event that is not one.
+    fn inline_run(&mut self) -> Vec<InlineElement> {
+        let mut buf = InlineBuf::default();
+        while let Some((event, _)) = self.events.next_if(|(e, _)| is_inline(e)) {
+            self.inline(event, &mut buf);
+        }
+        buf.finish()
+    }
+
+    fn inline(&mut self, event: Event<'a>, buf: &mut InlineBuf) {
+        match event {
+            Event::Text(text) => buf.text.push_str(&text),
+            Event::Code(code) => buf.push(InlineElement::Code(HTMLItem::new(code.to_string()))),
+            Event::Html(html) => {
+                buf.push(InlineElement::RawInline("html".into(), html.to_string()))
+            }
+            Event::SoftBreak => buf.push(InlineElement::SoftBreak),
+            Event::HardBreak => buf.push(InlineElement::LineBreak),
+            Event::FootnoteReference(label) => match self.footnotes.get(label.as_ref()) {
+                Some(blocks) => buf.push(InlineElement::Note(blocks.clone())),
+                None => buf.text.push_str(&format!("[^{}]", label)),
+            },
+            Event::TaskListMarker(done) => {
+                buf.push(InlineElement::Task(if done { 'x' } else { ' ' }));
+                buf.push(InlineElement::Space);
+            }
+            Event::Start(Tag::Emphasis) => buf.push(InlineElement::Emph(self.inlines())),
+            Event::Start(Tag::Strong) => buf.push(InlineElement::Strong(self.inlines())),
+            Event::Start(Tag::Strikethrough) => buf.push(InlineElement::Strikeout(self.inlines())),
+            Event::Start(Tag::Link(kind, url, title)) => {
+                let mut item = HTMLItem::new(self.inlines());
+                let url = match kind {
+                    LinkType::Autolink => {
+                        item.classes.push("uri".into());
+                        url.to_string()
+                    }
+                    LinkType::Email => {
+                        item.classes.push("email".into());
+                        format!("mailto:{}", url)
+                    }
+                    _ => url.to_string(),
+                };
+                buf.push(InlineElement::Link(item, (url, title.to_string())));
+            }
+            Event::Start(Tag::Image(_, url, title)) => {
+                let item = HTMLItem::new(self.inlines());
+                buf.push(InlineElement::Image(
+                    item,
+                    (url.to_string(), title.to_string()),
+                ));
+            }
+            Event::Start(_) => self.skip(),
+            _ => {}
+        }
+    }
+
+    /// The identifier pandoc would give a header with this text.
+    fn auto_ident(&mut self, text: &str) -> String {
+        let ident = ident(text);
+        let count = self.idents.entry(ident.clone()).or_insert(0);
+        *count += 1;
+        match *count {
+            1 => ident,
+            n => format!("{}-{}", ident, n - 1),
+        }
+    }
+}
+
+/// Split text into words and spaces, picking out Obsidian syntax on the way.
+fn obsidian_inlines(text: &str) -> Vec<InlineElement> {
+    let mut inlines = Vec::new();
+    let mut last = 0;
+    for c in OBSIDIAN_RE.captures_iter(text) {
+        let Some(whole) = c.get(0) else { continue };
+        let inline = if let Some(link) = c.name("link") {
+            let mut link = Link::wiki(link.as_str());
+            link.embed = c.name("embed").is_some();
+            match link.embed {
+                true => InlineElement::Embed(link),
+                false => InlineElement::WikiLink(link),
+            }
+        } else if let Some(mark) = c.name("mark") {
+            InlineElement::Highlight(tidy(obsidian_inlines(mark.as_str())))
+        } else if let Some(comment) = c.name("comment") {
+            InlineElement::Comment(comment.as_str().trim().into())
+        } else if let Some(math) = c.name("display") {
+            InlineElement::Math(MathItem::Display(math.as_str().trim().into()))
+        } else if let Some(math) = c.name("math") {
+            InlineElement::Math(MathItem::Inline(math.as_str().into()))
+        } else if let (Some(key), Some(value)) = (
+            c.name("key").or(c.name("pkey")),
+            c.name("value").or(c.name("pvalue")),
+        ) {
+            InlineElement::Field(key.as_str().trim().into(), value.as_str().trim().into())
+        } else if let Some(tag) = c.name("tag") {
+            // Only what `markdown::hashtags` accepts, so `#123` stays text
+            match markdown::hashtags(tag.as_str()).first() {
+                Some(tag) => InlineElement::Tag(tag.to_string()),
+                None => continue,
+            }
+        } else if let Some(block) = c.name("block") {
+            InlineElement::BlockId(block.as_str().into())
+        } else {
+            continue;
+        };
+        // Whitespace matched in front of a tag or block id stays text
+        let start = c
+            .name("pre")
+            .or(c.name("bpre"))
+            .map(|p| p.end())
+            .unwrap_or(whole.start());
+        inlines.extend(words(&text[last..start]));
+        inlines.push(inline);
+        last = whole.end();
+    }
+    inlines.extend(words(&text[last..]));
+    inlines
+}
+
+pub fn words(text: &str) -> Vec<InlineElement> {
+    let mut inlines = Vec::new();
+    let mut word = String::new();
+    for c in text.chars() {
+        if c.is_whitespace() {
+            if !word.is_empty() {
+                inlines.push(InlineElement::Str(std::mem::take(&mut word)));
+            }
+            inlines.push(InlineElement::Space);
+        } else {
+            word.push(c);
+        }
+    }
+    if !word.is_empty() {
+        inlines.push(InlineElement::Str(word));
+    }
+    inlines
+}
+
+/// Wrap `==` pairs around other elements, like `==some **bold**==`, which
+/// the text scan cannot see as one piece.
+fn highlights(items: Vec<InlineElement>) -> Vec<InlineElement> {
+    let opens = |i: &InlineElement| matches!(i, InlineElement::Str(s) if s.len() > 2 && s.starts_with("=="));
+    let closes = |i: &InlineElement| matches!(i, InlineElement::Str(s) if s.ends_with("=="));
+    let mut out = Vec::new();
+    let mut i = 0;
+    while i < items.len() {
+        let close = match opens(&items[i]) {
+            true => (i + 1..items.len()).find(|&j| closes(&items[j])),
+            false => None,
+        };
+        match close {
+            Some(j) => {
+                let mut inner = items[i..=j].to_vec();
+                if let Some(InlineElement::Str(s)) = inner.first_mut() {
+                    s.drain(..2);
+                }
+                if let Some(InlineElement::Str(s)) = inner.last_mut() {
+                    s.truncate(s.len() - 2);
+                }
+                inner.retain(|i| !matches!(i, InlineElement::Str(s) if s.is_empty()));
+                out.push(InlineElement::Highlight(tidy(inner)));
+                i = j + 1;
+            }
+            None => {
+                out.push(items[i].clone());
+                i += 1;
+            }
+        }
+    }
+    out
+}
+
+/// Merge neighbouring words and spaces, and trim spaces at both ends.
+pub fn tidy(items: Vec<InlineElement>) -> Vec<InlineElement> {
+    let mut tidy: Vec<InlineElement> = Vec::new();
+    for item in items {
+        match (tidy.last_mut(), item) {
+            (
+                None | Some(InlineElement::Space | InlineElement::SoftBreak),
+                InlineElement::Space,
+            ) => {}
+            (Some(InlineElement::Str(a)), InlineElement::Str(b)) => a.push_str(&b),
+            (Some(last @ InlineElement::Space), br @ InlineElement::SoftBreak) => *last = br,
+            (_, item) => tidy.push(item),
+        }
+    }
+    while matches!(
+        tidy.last(),
+        Some(InlineElement::Space | InlineElement::SoftBreak)
+    ) {
+        tidy.pop();
+    }
+    tidy
+}
+
+/// Turn `> [!kind] title` quotes into callouts, leaving other quotes as they are.
+fn callout(blocks: Vec<BlockElement>) -> BlockElement {
+    let header = match blocks.first() {
+        Some(BlockElement::Para(inlines)) => match inlines.first() {
+            Some(InlineElement::Str(s)) => CALLOUT_RE.captures(s),
+            _ => None,
+        },
+        _ => None,
+    };
+    let Some(c) = header else {
+        return BlockElement::BlockQuote(blocks);
+    };
+    let kind = c["kind"].to_lowercase();
+    let fold = c.name("fold").and_then(|f| f.as_str().chars().next());
+    let rest = c["rest"].to_string();
+    let mut blocks = blocks.into_iter();
+    let Some(BlockElement::Para(inlines)) = blocks.next() else {
+        return BlockElement::BlockQuote(Vec::new());
+    };
+    // The title runs to the end of the first line, the body starts after it
+    let mut inlines = inlines.into_iter().skip(1);
+    let mut title: Vec<InlineElement> = Vec::new();
+    if !rest.is_empty() {
+        title.push(InlineElement::Str(rest));
+    }
+    for inline in inlines.by_ref() {
+        match inline {
+            InlineElement::SoftBreak | InlineElement::LineBreak => break,
+            inline => title.push(inline),
+        }
+    }
+    let body: Vec<InlineElement> = inlines.collect();
+    let mut content = Vec::new();
+    if !body.is_empty() {
+        content.push(BlockElement::Para(body));
+    }
+    content.extend(blocks);
+    BlockElement::Callout(Callout {
+        kind,
+        fold,
+        title: tidy(title),
+        content,
+    })
+}
+
+/// Pick up task statuses that pulldown-cmark does not know, such as `[/]` and `[-]`.
+fn task_item(mut blocks: Vec<BlockElement>) -> Vec<BlockElement> {
+    if let Some(BlockElement::Plain(inlines) | BlockElement::Para(inlines)) = blocks.first_mut() {
+        let status = match inlines.as_slice() {
+            [InlineElement::Str(s), InlineElement::Space, ..] | [InlineElement::Str(s)] => {
+                TASK_RE.captures(s).and_then(|c| c["status"].chars().next())
+            }
+            _ => None,
+        };
+        if let Some(status) = status {
+            inlines[0] = InlineElement::Task(status);
+        }
+    }
+    blocks
+}
+
+/// The identifier pandoc derives from the text of a heading, before `-1`,
+/// `-2`... are added to tell apart headings of the same text.
+pub fn ident(text: &str) -> String {
+    let ident: String = text
+        .chars()
+        .filter_map(|c| match c {
+            c if c.is_whitespace() => Some('-'),
+            c if c.is_alphanumeric() || matches!(c, '_' | '-' | '.') => Some(c),
+            _ => None,
+        })
+        .flat_map(char::to_lowercase)
+        .skip_while(|c| !c.is_alphabetic())
+        .collect();
+    match ident.is_empty() {
+        true => "section".into(),
+        false => ident,
+    }
+}
+
+/// The plain text of inlines, as pandoc's `stringify`.
+pub fn stringify(inlines: &[InlineElement]) -> String {
+    inlines
+        .iter()
+        .map(|inline| match inline {
+            InlineElement::Str(s) | InlineElement::Tag(s) => s.clone(),
+            InlineElement::Space | InlineElement::SoftBreak | InlineElement::LineBreak => {
+                " ".into()
+            }
+            InlineElement::Emph(i)
+            | InlineElement::Strong(i)
+            | InlineElement::Strikeout(i)
+            | InlineElement::Highlight(i) => stringify(i),
+            InlineElement::Span(item) => stringify(&item.children),
+            InlineElement::Link(item, _) | InlineElement::Image(item, _) => {
+                stringify(&item.children)
+            }
+            InlineElement::Code(item) => item.children.clone(),
+            InlineElement::Math(MathItem::Display(m) | MathItem::Inline(m)) => m.clone(),
+            InlineElement::WikiLink(link) => link_text(link),
+            InlineElement::Field(key, value) => format!("{}:: {}", key, value),
+            InlineElement::RawInline(..)
+            | InlineElement::Note(_)
+            | InlineElement::Embed(_)
+            | InlineElement::Comment(_)
+            | InlineElement::BlockId(_)
+            | InlineElement::Task(_) => String::new(),
+        })
+        .collect()
+}
+
+/// The text Obsidian shows for a wikilink: its alias, or the target and heading.
+pub fn link_text(link: &Link) -> String {
+    if let Some(alias) = &link.alias {
+        return alias.clone();
+    }
+    let mut text = link.target.clone();
+    if let Some(h) = &link.heading {
+        text = format!("{} > {}", text, h).trim_start_matches(" > ").into();
+    }
+    if let Some(b) = &link.block {
+        text = format!("{} > ^{}", text, b)
+            .trim_start_matches(" > ")
+            .into();
+    }
+    text
+}
+
+impl DocumentAST {
+    /// Parse the markdown body of a note, without its frontmatter.
+    pub fn parse(body: &str, meta: Metadata) -> Self {
+        let options = Options::ENABLE_TABLES
+            | Options::ENABLE_FOOTNOTES
+            | Options::ENABLE_STRIKETHROUGH
+            | Options::ENABLE_TASKLISTS
+            | Options::ENABLE_HEADING_ATTRIBUTES;
+        // Footnote definitions are parsed first, so references can hold their content
+        let mut main = Vec::new();
+        let mut definitions: Vec<(String, Vec<Spanned>)> = Vec::new();
+        let mut current: Option<(String, Vec<Spanned>)> = None;
+        for (event, range) in Parser::new_ext(body, options).into_offset_iter() {
+            match (&event, current.is_some()) {
+                (Event::Start(Tag::FootnoteDefinition(label)), false) => {
+                    current = Some((label.to_string(), Vec::new()));
+                    continue;
+                }
+                (Event::End(Tag::FootnoteDefinition(_)), true) => {
+                    definitions.extend(current.take());
+                    continue;
+                }
+                _ => {}
+            }
+            match &mut current {
+                Some((_, events)) => events.push((event, range)),
+                None => main.push((event, range)),
+            }
+        }
+        let none = HashMap::new();
+        let footnotes: HashMap<String, Vec<BlockElement>> = definitions
+            .into_iter()
+            .map(|(label, events)| (label, Builder::new(body, events, &none).blocks()))
+            .collect();
+        let content = Builder::new(body, main, &footnotes).blocks();
+        Self { meta, content }
+    }
+}
+
+/// An outline of the tree, one block per line with its inlines after it.
+impl Display for DocumentAST {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let mut keys: Vec<&String> = self.meta.keys().collect();
+        keys.sort();
+        for key in keys {
+            writeln!(f, "Meta {:?} {:?}", key, self.meta[key].to_string())?;
+        }
+        for block in &self.content {
+            block.outline(f, 0)?;
+        }
+        Ok(())
+    }
+}
+
+fn attr_outline<T>(item: &HTMLItem<T>) -> String {
+    format!("({:?}, {:?}, {:?})", item.ident, item.classes, item.attrs)
+}
+
+fn inlines_outline(inlines: &[InlineElement]) -> String {
+    inlines
+        .iter()
+        .map(|i| i.to_string())
+        .collect::<Vec<String>>()
+        .join(" ")
+}
+
+impl Display for InlineElement {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            InlineElement::Str(s) => write!(f, "Str {:?}", s),
+            InlineElement::Emph(i) => write!(f, "Emph [{}]", inlines_outline(i)),
+            InlineElement::Strong(i) => write!(f, "Strong [{}]", inlines_outline(i)),
+            InlineElement::Strikeout(i) => write!(f, "Strikeout [{}]", inlines_outline(i)),
+            InlineElement::Code(item) => {
+                write!(f, "Code {} {:?}", attr_outline(item), item.children)
+            }
+            InlineElement::Space => write!(f, "Space"),
+            InlineElement::SoftBreak => write!(f, "SoftBreak"),
+            InlineElement::LineBreak => write!(f, "LineBreak"),
+            InlineElement::Math(MathItem::Display(m)) => write!(f, "Math Display {:?}", m),
+            InlineElement::Math(MathItem::Inline(m)) => write!(f, "Math Inline {:?}", m),
+            InlineElement::RawInline(format, text) => write!(f, "RawInline {} {:?}", format, text),
+            InlineElement::Link(item, (url, title)) => write!(
+                f,
+                "Link {} [{}] ({:?}, {:?})",
+                attr_outline(item),
+                inlines_outline(&item.children),
+                url,
+                title
+            ),
+            InlineElement::Image(item, (url, title)) => write!(
+                f,
+                "Image {} [{}] ({:?}, {:?})",
+                attr_outline(item),
+                inlines_outline(&item.children),
+                url,
+                title
+            ),
+            InlineElement::Note(blocks) => write!(f, "Note [{} blocks]", blocks.len()),
+            InlineElement::Span(item) => write!(
+                f,
+                "Span {} [{}]",
+                attr_outline(item),
+                inlines_outline(&item.children)
+            ),
+            InlineElement::WikiLink(link) => write!(f, "WikiLink {}", link),
+            InlineElement::Embed(link) => write!(f, "Embed {}", link),
+            InlineElement::Tag(tag) => write!(f, "Tag {:?}", tag),
+            InlineElement::Highlight(i) => write!(f, "Highlight [{}]", inlines_outline(i)),
+            InlineElement::Comment(c) => write!(f, "Comment {:?}", c),
+            InlineElement::BlockId(id) => write!(f, "BlockId {:?}", id),
+            InlineElement::Field(key, value) => write!(f, "Field {:?} {:?}", key, value),
+            InlineElement::Task(status) => write!(f, "Task {:?}", status),
+        }
+    }
+}
+
+impl BlockElement {
+    fn outline(&self, f: &mut Formatter<'_>, depth: usize) -> std::fmt::Result {
+        let indent = "  ".repeat(depth);
+        let items = |f: &mut Formatter<'_>, items: &[Vec<BlockElement>]| {
+            for (n, item) in items.iter().enumerate() {
+                writeln!(f, "{}  - item {}", indent, n + 1)?;
+                for block in item {
+                    block.outline(f, depth + 2)?;
+                }
+            }
+            Ok(())
+        };
+        match self {
+            BlockElement::Plain(i) => writeln!(f, "{}Plain [{}]", indent, inlines_outline(i)),
+            BlockElement::Para(i) => writeln!(f, "{}Para [{}]", indent, inlines_outline(i)),
+            BlockElement::CodeBlock(item) => writeln!(
+                f,
+                "{}CodeBlock {} {:?}",
+                indent,
+                attr_outline(item),
+                item.children
+            ),
+            BlockElement::RawBlock(format, text) => {
+                writeln!(f, "{}RawBlock {} {:?}", indent, format, text)
+            }
+            BlockElement::BlockQuote(blocks) => {
+                writeln!(f, "{}BlockQuote", indent)?;
+                blocks.iter().try_for_each(|b| b.outline(f, depth + 1))
+            }
+            BlockElement::OrderedList((start, delim), list) => {
+                writeln!(f, "{}OrderedList ({}, {:?})", indent, start, delim)?;
+                items(f, list)
+            }
+            BlockElement::BulletList(list) => {
+                writeln!(f, "{}BulletList", indent)?;
+                items(f, list)
+            }
+            BlockElement::Header(level, item) => writeln!(
+                f,
+                "{}Header {} {} [{}]",
+                indent,
+                level,
+                attr_outline(item),
+                inlines_outline(&item.children)
+            ),
+            BlockElement::HorizontalRule => writeln!(f, "{}HorizontalRule", indent),
+            BlockElement::Table(table) => {
+                writeln!(f, "{}Table {:?}", indent, table.aligns)?;
+                for row in std::iter::once(&table.head).chain(&table.rows) {
+                    let cells: Vec<String> = row
+                        .iter()
+                        .map(|c| format!("[{}]", inlines_outline(c)))
+                        .collect();
+                    writeln!(f, "{}  | {}", indent, cells.join(" | "))?;
+                }
+                Ok(())
+            }
+            BlockElement::Callout(c) => {
+                writeln!(
+                    f,
+                    "{}Callout {:?} {:?} [{}]",
+                    indent,
+                    c.kind,
+                    c.fold,
+                    inlines_outline(&c.title)
+                )?;
+                c.content.iter().try_for_each(|b| b.outline(f, depth + 1))
+            }
+            BlockElement::Div(item) => {
+                writeln!(f, "{}Div {}", indent, attr_outline(item))?;
+                item.children
+                    .iter()
+                    .try_for_each(|b| b.outline(f, depth + 1))
+            }
+            BlockElement::Comment(c) => writeln!(f, "{}Comment {:?}", indent, c),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn outline(body: &str) -> String {
+        DocumentAST::parse(body, Metadata::new()).to_string()
+    }
+
+    #[test]
+    fn callouts() {
+        assert_eq!(
+            outline("> [!Warning]- Be *careful*\n> body\n"),
+            "Callout \"warning\" Some('-') [Str \"Be\" Space Emph [Str \"careful\"]]\n  \
+             Para [Str \"body\"]\n"
+        );
+        assert_eq!(
+            outline("> [!tip]+\n> body\n"),
+            "Callout \"tip\" Some('+') []\n  Para [Str \"body\"]\n"
+        );
+        assert_eq!(outline("> quote\n"), "BlockQuote\n  Para [Str \"quote\"]\n");
+    }
+
+    #[test]
+    fn tasks_and_nested_lists() {
+        assert_eq!(
+            outline("- [ ] open\n- [x] done\n- [/] half\n  1) nested\n  2) two\n"),
+            "BulletList\n\
+             \x20 - item 1\n    Plain [Task ' ' Space Str \"open\"]\n\
+             \x20 - item 2\n    Plain [Task 'x' Space Str \"done\"]\n\
+             \x20 - item 3\n    Plain [Task '/' Space Str \"half\"]\n\
+             \x20   OrderedList (1, OneParen)\n\
+             \x20     - item 1\n        Plain [Str \"nested\"]\n\
+             \x20     - item 2\n        Plain [Str \"two\"]\n"
+        );
+    }
+
+    #[test]
+    fn footnotes_hold_their_definition() {
+        let doc = DocumentAST::parse("Text[^1] more\n\n[^1]: The *note*.\n", Metadata::new());
+        let [BlockElement::Para(inlines)] = doc.content.as_slice() else {
+            panic!("{}", doc);
+        };
+        assert_eq!(inlines[0], InlineElement::Str("Text".into()));
+        let InlineElement::Note(blocks) = &inlines[1] else {
+            panic!("{}", doc);
+        };
+        assert_eq!(
+            blocks,
+            &[BlockElement::Para(vec![
+                InlineElement::Str("The".into()),
+                InlineElement::Space,
+                InlineElement::Emph(vec![InlineElement::Str("note".into())]),
+                InlineElement::Str(".".into()),
+            ])]
+        );
+        // A reference without a definition stays as text
+        assert_eq!(
+            outline("See [^x]\n"),
+            "Para [Str \"See\" Space Str \"[^x]\"]\n"
+        );
+    }
+
+    #[test]
+    fn highlight_across_inline_markup() {
+        assert_eq!(
+            outline("==a *b* c== ==[[Note]]=="),
+            "Para [Highlight [Str \"a\" Space Emph [Str \"b\"] Space Str \"c\"] Space \
+             Highlight [WikiLink [[Note]]]]\n"
+        );
+        assert_eq!(
+            outline("a == b"),
+            "Para [Str \"a\" Space Str \"==\" Space Str \"b\"]\n"
+        );
+    }
+
+    #[test]
+    fn tables_with_alignment() {
+        assert_eq!(
+            outline("| a | b | c | d |\n|:--|--:|:-:|---|\n| 1 | **2** | 3 | 4 |\n"),
+            "Table [Left, Right, Center, Default]\n  \
+             | [Str \"a\"] | [Str \"b\"] | [Str \"c\"] | [Str \"d\"]\n  \
+             | [Str \"1\"] | [Strong [Str \"2\"]] | [Str \"3\"] | [Str \"4\"]\n"
+        );
+    }
+
+    #[test]
+    fn headings_and_obsidian_inlines() {
+        assert_eq!(
+            outline("## Über den #tag\n\nSee ![[Pic.png]] %%hidden%% [due:: 2024-03-01] ^id1\n"),
+            "Header 2 (\"über-den-tag\", [], []) [Str \"Über\" Space Str \"den\" Space Tag \"#tag\"]\n\
+             Para [Str \"See\" Space Embed ![[Pic.png]] Space Comment \"hidden\" Space \
+             Field \"due\" \"2024-03-01\" Space BlockId \"id1\"]\n"
+        );
+    }
+
+    #[test]
+    fn malformed_input_does_not_panic() {
+        assert_eq!(
+            outline("**unclosed *emph\n"),
+            "Para [Str \"**unclosed\" Space Str \"*emph\"]\n"
+        );
+        assert_eq!(
+            outline("```\nno end\n"),
+            "CodeBlock (\"\", [], []) \"no end\"\n"
+        );
+        for body in [
+            "",
+            "> [!\n",
+            "> [!]\n",
+            "| a |\n|--|\n| 1 | 2 |\n",
+            "[^y]: [^y]\n",
+            "[[\n]]",
+            "![[]] [[|]] [[#]] [[#^]]",
+            "==\n==",
+            "$$ $ %% ^",
+            "- [ ]\n-\n  -\n    - [x]",
+            "<div>\n*html*\n</div>\n<span>",
+            ">>>>>>>>>> deep\n",
+            "# \n######## seven\n\r\n\t\u{0}\u{feff}",
+            "[link](<unclosed [text]( ![img](",
+        ] {
+            let _ = outline(body);
+        }
+    }
+}
@@ -0,0 +1,916 @@
+use crate::date::{self, parse_date_arg};
+use crate::links::Link;
+use crate::tags;
+use crate::value::Value;
+use crate::FileData;
+use chrono::NaiveDateTime;
+use std::cmp::Ordering;
+use std::fmt::{Display, Formatter};
+use std::path::Path;
+
+/// A query in the subset of Dataview's query language (DQL) that is supported:
+///
+/// ```text
+/// LIST [expr] | TABLE [WITHOUT ID] expr [AS name], ...
+/// [FROM #tag | "folder" | -source | source and/or source]
+/// [WHERE expr] [SORT expr [ASC|DESC], ...] [LIMIT n]
+/// ```
+///
+/// `WHERE`, `SORT` and `LIMIT` may come in any order but always run in that
+/// order, and several `WHERE` clauses must all hold.
+pub struct Query {
+    pub kind: QueryKind,
+    pub from: Option<Source>,
+    pub filter: Option<Expr>,
+    /// Sort keys, each with `true` for descending
+    pub sort: Vec<(Expr, bool)>,
+    pub limit: Option<usize>,
+}
+
+pub enum QueryKind {
+    /// One note per line, with an optional value after it
+    List(Option<Expr>),
+    /// Columns with their header, and whether to leave out the note column
+    Table {
+        columns: Vec<(Expr, String)>,
+        without_id: bool,
+    },
+}
+
+/// The notes a query starts from.
+pub enum Source {
+    /// `#tag`, also matching tags nested below it
+    Tag(String),
+    /// `"folder"`, or the path of a single note
+    Folder(String),
+    Not(Box<Source>),
+    And(Box<Source>, Box<Source>),
+    Or(Box<Source>, Box<Source>),
+}
+
+#[derive(Clone, Copy)]
+pub enum CompareOp {
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+}
+
+pub enum Expr {
+    Literal(Value),
+    /// A field of the note such as `status`, or an implicit one such as `file.name`
+    Field(String),
+    Not(Box<Expr>),
+    And(Box<Expr>, Box<Expr>),
+    Or(Box<Expr>, Box<Expr>),
+    Compare(Box<Expr>, CompareOp, Box<Expr>),
+    /// `contains`, `length`, `lower`, `upper`, `date` or `default`
+    Call(String, Vec<Expr>),
+}
+
+/// A query that could not be parsed, with the byte offset of the problem.
+#[derive(Debug)]
+pub struct QueryError {
+    pub message: String,
+    pub at: usize,
+}
+
+impl Display for QueryError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} at position {}", self.message, self.at + 1)
+    }
+}
+
+impl std::error::Error for QueryError {}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    /// Keywords, field names and function names
+    Word(String),
+    Str(String),
+    Number(f64),
+    Tag(String),
+    /// The inside of `[[...]]`
+    Link(String),
+    Symbol(&'static str),
+}
+
+impl Display for Token {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Token::Word(w) => write!(f, "`{}`", w),
+            Token::Str(s) => write!(f, "\"{}\"", s),
+            Token::Number(n) => write!(f, "`{}`", n),
+            Token::Tag(t) => write!(f, "`{}`", t),
+            Token::Link(l) => write!(f, "`[[{}]]`", l),
+            Token::Symbol(s) => write!(f, "`{}`", s),
+        }
+    }
+}
+
+const SYMBOLS: [&str; 11] = ["!=", "<=", ">=", "=", "<", ">", "!", "(", ")", ",", "-"];
+
+fn tokenize(input: &str) -> Result<Vec<(Token, usize)>, QueryError> {
+    let mut tokens = Vec::new();
+    let mut chars = input.char_indices().peekable();
+    while let Some(&(at, c)) = chars.peek() {
+        let rest = &input[at..];
+        let word_char = |c: char| c.is_alphanumeric() || matches!(c, '_' | '-' | '.');
+        if c.is_whitespace() {
+            chars.next();
+        } else if c == '"' {
+            chars.next();
+            let mut text = String::new();
+            loop {
+                match chars.next() {
+                    Some((_, '"')) => break,
+                    Some((_, '\\')) => text.extend(chars.next().map(|(_, c)| c)),
+                    Some((_, c)) => text.push(c),
+                    None => {
+                        return Err(QueryError {
+                            message: "unterminated string".into(),
+                            at,
+                        })
+                    }
+                }
+            }
+            tokens.push((Token::Str(text), at));
+        } else if let Some(inner) = rest.strip_prefix("[[") {
+            let Some(end) = inner.find("]]") else {
+                return Err(QueryError {
+                    message: "unterminated link".into(),
+                    at,
+                });
+            };
+            tokens.push((Token::Link(inner[..end].into()), at));
+            while chars.peek().is_some_and(|&(i, _)| i < at + end + 4) {
+                chars.next();
+            }
+        } else if c == '#' {
+            let len = rest[1..]
+                .find(|c: char| !c.is_alphanumeric() && !matches!(c, '_' | '-' | '/'))
+                .unwrap_or(rest.len() - 1);
+            tokens.push((Token::Tag(rest[..len + 1].into()), at));
+            for _ in rest[..len + 1].chars() {
+                chars.next();
+            }
+        } else if c.is_ascii_digit() {
+            let len = rest
+                .find(|c: char| !c.is_ascii_digit() && c != '.')
+                .unwrap_or(rest.len());
+            let number = rest[..len].parse().map_err(|_| QueryError {
+                message: format!("`{}` is not a number", &rest[..len]),
+                at,
+            })?;
+            tokens.push((Token::Number(number), at));
+            for _ in rest[..len].chars() {
+                chars.next();
+            }
+        } else if c.is_alphabetic() || c == '_' {
+            let len = rest.find(|c| !word_char(c)).unwrap_or(rest.len());
+            tokens.push((Token::Word(rest[..len].into()), at));
+            for _ in rest[..len].chars() {
+                chars.next();
+            }
+        } else if let Some(s) = SYMBOLS.iter().find(|s| rest.starts_with(**s)) {
+            tokens.push((Token::Symbol(s), at));
+            for _ in s.chars() {
+                chars.next();
+            }
+        } else {
+            return Err(QueryError {
+                message: format!("unexpected `{}`", c),
+                at,
+            });
+        }
+    }
+    Ok(tokens)
+}
+
+struct Parser<'a> {
+    input: &'a str,
+    tokens: Vec<(Token, usize)>,
+    pos: usize,
+}
+
+impl Parser<'_> {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos).map(|(t, _)| t)
+    }
+
+    /// Byte offset of the next token, or the end of the input.
+    fn offset(&self) -> usize {
+        self.tokens
+            .get(self.pos)
+            .map(|(_, at)| *at)
+            .unwrap_or(self.input.len())
+    }
+
+    fn error<T>(&self, expected: &str) -> Result<T, QueryError> {
+        let found = match self.peek() {
+            Some(t) => t.to_string(),
+            None => "the end of the query".into(),
+        };
+        Err(QueryError {
+            message: format!("expected {}, found {}", expected, found),
+            at: self.offset(),
+        })
+    }
+
+    fn keyword(&mut self, keyword: &str) -> bool {
+        match self.peek() {
+            Some(Token::Word(w)) if w.eq_ignore_ascii_case(keyword) => {
+                self.pos += 1;
+                true
+            }
+            _ => false,
+        }
+    }
+
+    fn symbol(&mut self, symbol: &str) -> bool {
+        match self.peek() {
+            Some(Token::Symbol(s)) if *s == symbol => {
+                self.pos += 1;
+                true
+            }
+            _ => false,
+        }
+    }
+
+    fn expect_symbol(&mut self, symbol: &str) -> Result<(), QueryError> {
+        if self.symbol(symbol) {
+            Ok(())
+        } else {
+            self.error(&format!("`{}`", symbol))
+        }
+    }
+
+    /// Whether the next token starts a clause, which ends the one before it.
+    fn at_clause(&self) -> bool {
+        match self.peek() {
+            None => true,
+            Some(Token::Word(w)) => ["FROM", "WHERE", "SORT", "LIMIT", "FLATTEN", "GROUP"]
+                .iter()
+                .any(|k| w.eq_ignore_ascii_case(k)),
+            _ => false,
+        }
+    }
+
+    fn query(&mut self) -> Result<Query, QueryError> {
+        let kind = if self.keyword("LIST") {
+            QueryKind::List(if self.at_clause() {
+                None
+            } else {
+                Some(self.expr()?)
+            })
+        } else if self.keyword("TABLE") {
+            let without_id = self.keyword("WITHOUT");
+            if without_id && !self.keyword("ID") {
+                return self.error("`ID` after `WITHOUT`");
+            }
+            let mut columns = Vec::new();
+            while !self.at_clause() {
+                let start = self.offset();
+                let expr = self.expr()?;
+                let name = if self.keyword("AS") {
+                    match self.tokens.get(self.pos) {
+                        Some((Token::Str(s) | Token::Word(s), _)) => {
+                            let s = s.clone();
+                            self.pos += 1;
+                            s
+                        }
+                        _ => return self.error("a column name after `AS`"),
+                    }
+                } else {
+                    self.input[start..self.offset()].trim().to_string()
+                };
+                columns.push((expr, name));
+                if !self.symbol(",") {
+                    break;
+                }
+            }
+            QueryKind::Table {
+                columns,
+                without_id,
+            }
+        } else {
+            return self.error("`LIST` or `TABLE`");
+        };
+        let from = if self.keyword("FROM") {
+            Some(self.source()?)
+        } else {
+            None
+        };
+        let mut query = Query {
+            kind,
+            from,
+            filter: None,
+            sort: Vec::new(),
+            limit: None,
+        };
+        while self.peek().is_some() {
+            if self.keyword("WHERE") {
+                let expr = self.expr()?;
+                query.filter = Some(match query.filter.take() {
+                    Some(f) => Expr::And(Box::new(f), Box::new(expr)),
+                    None => expr,
+                });
+            } else if self.keyword("SORT") {
+                loop {
+                    let expr = self.expr()?;
+                    let descending = self.keyword("DESC") || self.keyword("DESCENDING");
+                    if !descending && !self.keyword("ASC") {
+                        self.keyword("ASCENDING");
+                    }
+                    query.sort.push((expr, descending));
+                    if !self.symbol(",") {
+                        break;
+                    }
+                }
+            } else if self.keyword("LIMIT") {
+                match self.peek() {
+                    Some(Token::Number(n)) if n.fract() == 0.0 && *n >= 0.0 => {
+                        query.limit = Some(*n as usize);
+                        self.pos += 1;
+                    }
+                    _ => return self.error("a number after `LIMIT`"),
+                }
+            } else if self.at_clause() {
+                return Err(QueryError {
+                    message: "only `WHERE`, `SORT` and `LIMIT` are supported".into(),
+                    at: self.offset(),
+                });
+            } else {
+                return self.error("`WHERE`, `SORT` or `LIMIT`");
+            }
+        }
+        Ok(query)
+    }
+
+    fn source(&mut self) -> Result<Source, QueryError> {
+        let mut left = self.source_and()?;
+        while self.keyword("or") {
+            left = Source::Or(Box::new(left), Box::new(self.source_and()?));
+        }
+        Ok(left)
+    }
+
+    fn source_and(&mut self) -> Result<Source, QueryError> {
+        let mut left = self.source_term()?;
+        while self.keyword("and") {
+            left = Source::And(Box::new(left), Box::new(self.source_term()?));
+        }
+        Ok(left)
+    }
+
+    fn source_term(&mut self) -> Result<Source, QueryError> {
+        if self.symbol("-") || self.symbol("!") {
+            return Ok(Source::Not(Box::new(self.source_term()?)));
+        }
+        if self.symbol("(") {
+            let source = self.source()?;
+            self.expect_symbol(")")?;
+            return Ok(source);
+        }
+        let source = match self.peek() {
+            Some(Token::Tag(t)) => Source::Tag(t.clone()),
+            Some(Token::Str(s)) => Source::Folder(s.clone()),
+            _ => return self.error("`#tag` or `\"folder\"`"),
+        };
+        self.pos += 1;
+        Ok(source)
+    }
+
+    fn expr(&mut self) -> Result<Expr, QueryError> {
+        let mut left = self.and()?;
+        while self.keyword("or") {
+            left = Expr::Or(Box::new(left), Box::new(self.and()?));
+        }
+        Ok(left)
+    }
+
+    fn and(&mut self) -> Result<Expr, QueryError> {
+        let mut left = self.not()?;
+        while self.keyword("and") {
+            left = Expr::And(Box::new(left), Box::new(self.not()?));
+        }
+        Ok(left)
+    }
+
+    fn not(&mut self) -> Result<Expr, QueryError> {
+        if self.symbol("!") {
+            return Ok(Expr::Not(Box::new(self.not()?)));
+        }
+        self.comparison()
+    }
+
+    fn comparison(&mut self) -> Result<Expr, QueryError> {
+        let left = self.primary()?;
+        let op = match self.peek() {
+            Some(Token::Symbol("=")) => CompareOp::Eq,
+            Some(Token::Symbol("!=")) => CompareOp::Ne,
+            Some(Token::Symbol("<")) => CompareOp::Lt,
+            Some(Token::Symbol("<=")) => CompareOp::Le,
+            Some(Token::Symbol(">")) => CompareOp::Gt,
+            Some(Token::Symbol(">=")) => CompareOp::Ge,
+            _ => return Ok(left),
+        };
+        self.pos += 1;
+        Ok(Expr::Compare(Box::new(left), op, Box::new(self.primary()?)))
+    }
+
+    fn primary(&mut self) -> Result<Expr, QueryError> {
+        let Some(token) = self.peek().cloned() else {
+            return self.error("a value");
+        };
+        self.pos += 1;
+        Ok(match token {
+            Token::Str(s) => Expr::Literal(Value::Text(s)),
+            Token::Number(n) => Expr::Literal(Value::Number(n)),
+            Token::Tag(t) => Expr::Literal(Value::Text(t)),
+            Token::Link(l) => Expr::Literal(Value::Link(Link::wiki(&l))),
+            Token::Symbol("(") => {
+                let expr = self.expr()?;
+                self.expect_symbol(")")?;
+                expr
+            }
+            Token::Symbol("-") => match self.peek() {
+                Some(Token::Number(n)) => {
+                    let n = -n;
+                    self.pos += 1;
+                    Expr::Literal(Value::Number(n))
+                }
+                _ => return self.error("a number after `-`"),
+            },
+            Token::Word(w) if self.symbol("(") => {
+                let mut args = Vec::new();
+                if !self.symbol(")") {
+                    loop {
+                        args.push(self.expr()?);
+                        if self.symbol(")") {
+                            break;
+                        }
+                        self.expect_symbol(",")?;
+                    }
+                }
+                Expr::Call(w.to_lowercase(), args)
+            }
+            Token::Word(w) => match w.to_lowercase().as_str() {
+                "true" => Expr::Literal(Value::Bool(true)),
+                "false" => Expr::Literal(Value::Bool(false)),
+                "null" => Expr::Literal(Value::Null),
+                _ => Expr::Field(w),
+            },
+            Token::Symbol(_) => {
+                self.pos -= 1;
+                return self.error("a value");
+            }
+        })
+    }
+}
+
+/// Parse a query, see [Query] for what is supported.
+pub fn parse(input: &str) -> Result<Query, QueryError> {
+    let mut parser = Parser {
+        input,
+        tokens: tokenize(input)?,
+        pos: 0,
+    };
+    parser.query()
+}
+
+impl Source {
+    pub fn matches(&self, note: &FileData) -> bool {
+        match self {
+            Source::Tag(tag) => note.hashtags.iter().any(|t| tags::is_within(t, tag)),
+            Source::Folder(folder) => {
+                let folder = folder.trim_matches('/');
+                note.path.starts_with(folder)
+                    || note.path.with_extension("") == Path::new(folder)
+                    || note.path == Path::new(folder)
+            }
+            Source::Not(s) => !s.matches(note),
+            Source::And(a, b) => a.matches(note) && b.matches(note),
+            Source::Or(a, b) => a.matches(note) || b.matches(note),
+        }
+    }
+}
+
+impl Expr {
+    pub fn eval(&self, note: &FileData) -> Value {
+        match self {
+            Expr::Literal(v) => v.clone(),
+            Expr::Field(name) => field(note, name),
+            Expr::Not(e) => Value::Bool(!truthy(&e.eval(note))),
+            Expr::And(a, b) => Value::Bool(truthy(&a.eval(note)) && truthy(&b.eval(note))),
+            Expr::Or(a, b) => Value::Bool(truthy(&a.eval(note)) || truthy(&b.eval(note))),
+            Expr::Compare(a, op, b) => {
+                let ord = compare(&a.eval(note), &b.eval(note));
+                Value::Bool(match op {
+                    CompareOp::Eq => ord == Some(Ordering::Equal),
+                    CompareOp::Ne => ord != Some(Ordering::Equal),
+                    CompareOp::Lt => ord == Some(Ordering::Less),
+                    CompareOp::Le => matches!(ord, Some(Ordering::Less | Ordering::Equal)),
+                    CompareOp::Gt => ord == Some(Ordering::Greater),
+                    CompareOp::Ge => matches!(ord, Some(Ordering::Greater | Ordering::Equal)),
+                })
+            }
+            Expr::Call(name, args) => call(name, args, note),
+        }
+    }
+
+    /// Whether the note passes this expression as a `WHERE` clause.
+    pub fn test(&self, note: &FileData) -> bool {
+        truthy(&self.eval(note))
+    }
+}
+
+/// The value of a field, where `file.*` fields describe the note itself.
+pub fn field(note: &FileData, name: &str) -> Value {
+    let texts = |items: &[String]| Value::List(items.iter().cloned().map(Value::Text).collect());
+    match name {
+        "file.name" => Value::Text(
+            note.path
+                .file_stem()
+                .unwrap_or_default()
+                .to_string_lossy()
+                .into(),
+        ),
+        "file.path" => Value::Text(note.path.display().to_string()),
+        "file.folder" => Value::Text(
+            note.path
+                .parent()
+                .map(|p| p.display().to_string())
+                .unwrap_or_default(),
+        ),
+        "file.link" => Value::Link(Link::wiki(
+            &note.path.with_extension("").display().to_string(),
+        )),
+        "file.date" | "file.day" => match note.date_source {
+            Some(_) => Value::Date(note.date),
+            None => Value::Null,
+        },
+        "file.mtime" => note.mtime.map(Value::DateTime).unwrap_or(Value::Null),
+        "file.size" => Value::Number(note.size as f64),
+        "file.tags" => texts(&note.hashtags),
+        "file.aliases" => texts(&note.aliases),
+        "file.parents" => texts(&note.parents),
+        "file.outlinks" => Value::List(
+            note.links
+                .iter()
+                .filter(|l| l.is_internal())
+                .cloned()
+                .map(Value::Link)
+                .collect(),
+        ),
+        "file.tasks" => {
+            Value::Number(note.items.iter().filter(|i| i.status.is_some()).count() as f64)
+        }
+        _ => note
+            .metadata
+            .get(name)
+            .or_else(|| {
+                // Fields are also readable by their lowercase name, like `Status` as `status`
+                let lower = name.to_lowercase();
+                note.metadata
+                    .iter()
+                    .find(|(k, _)| k.to_lowercase() == lower)
+                    .map(|(_, v)| v)
+            })
+            .cloned()
+            .unwrap_or(Value::Null),
+    }
+}
+
+fn call(name: &str, args: &[Expr], note: &FileData) -> Value {
+    let arg = |i: usize| args.get(i).map(|a| a.eval(note)).unwrap_or(Value::Null);
+    match name {
+        "contains" => {
+            let needle = arg(1);
+            Value::Bool(match arg(0) {
+                Value::List(items) => items
+                    .iter()
+                    .any(|i| compare(i, &needle) == Some(Ordering::Equal)),
+                Value::Text(text) => text.contains(&needle.to_string()),
+                Value::Link(link) => link.target.contains(&needle.to_string()),
+                Value::Null => false,
+                other => compare(&other, &needle) == Some(Ordering::Equal),
+            })
+        }
+        "length" => Value::Number(match arg(0) {
+            Value::List(items) => items.len(),
+            Value::Text(text) => text.chars().count(),
+            Value::Null => 0,
+            _ => 1,
+        } as f64),
+        "lower" => Value::Text(arg(0).to_string().to_lowercase()),
+        "upper" => Value::Text(arg(0).to_string().to_uppercase()),
+        "date" => match args.first() {
+            // `date(today)` reads `today` as a word rather than a field
+            Some(Expr::Field(word)) if parse_date_arg(word).is_ok() && !word.contains('.') => {
+                parse_date_arg(word).map(Value::Date).unwrap_or(Value::Null)
+            }
+            _ => match arg(0) {
+                d @ Value::Date(_) => d,
+                Value::DateTime(dt) => Value::Date(dt.date()),
+                Value::Text(t) => parse_date_arg(&t).map(Value::Date).unwrap_or(Value::Null),
+                _ => Value::Null,
+            },
+        },
+        "default" => match arg(0) {
+            Value::Null => arg(1),
+            v => v,
+        },
+        _ => Value::Null,
+    }
+}
+
+fn truthy(value: &Value) -> bool {
+    match value {
+        Value::Null => false,
+        Value::Bool(b) => *b,
+        Value::Number(n) => *n != 0.0,
+        Value::Text(t) => !t.is_empty(),
+        Value::List(items) => !items.is_empty(),
+        _ => true,
+    }
+}
+
+fn datetime(value: &Value) -> Option<NaiveDateTime> {
+    match value {
+        Value::Date(d) => d.and_hms_opt(0, 0, 0),
+        Value::DateTime(dt) => Some(*dt),
+        Value::Text(t) => date::parse_date(t)
+            .and_then(|d| d.and_hms_opt(0, 0, 0))
+            .or_else(|| date::parse_datetime(t)),
+        _ => None,
+    }
+}
+
+/// Compare two values of the same kind, `None` when they cannot be compared.
+///
+/// Dates compare with dates written as text, and links compare by their
+/// target, ignoring case, with links and text.
+pub fn compare(a: &Value, b: &Value) -> Option<Ordering> {
+    match (a, b) {
+        (Value::Null, Value::Null) => Some(Ordering::Equal),
+        (Value::Number(x), Value::Number(y)) => x.partial_cmp(y),
+        (Value::Bool(x), Value::Bool(y)) => Some(x.cmp(y)),
+        (Value::Link(x), Value::Link(y)) => {
+            Some(x.target.to_lowercase().cmp(&y.target.to_lowercase()))
+        }
+        (Value::Link(x), Value::Text(y)) => Some(x.target.to_lowercase().cmp(&y.to_lowercase())),
+        (Value::Text(x), Value::Link(y)) => Some(x.to_lowercase().cmp(&y.target.to_lowercase())),
+        (Value::List(x), Value::List(y)) => {
+            for (i, j) in x.iter().zip(y) {
+                match compare(i, j)? {
+                    Ordering::Equal => continue,
+                    other => return Some(other),
+                }
+            }
+            Some(x.len().cmp(&y.len()))
+        }
+        (Value::Date(_) | Value::DateTime(_), _) | (_, Value::Date(_) | Value::DateTime(_)) => {
+            Some(datetime(a)?.cmp(&datetime(b)?))
+        }
+        (Value::Text(x), Value::Text(y)) => Some(x.cmp(y)),
+        _ => None,
+    }
+}
+
+/// A total order for sorting: values are grouped by type, with missing
+/// values first, then ordered within their type. Text and links both ignore
+/// case, with the exact text breaking ties.
+pub fn sort_order(a: &Value, b: &Value) -> Ordering {
+    let rank = |v: &Value| match v {
+        Value::Null => 0,
+        Value::Bool(_) => 1,
+        Value::Number(_) => 2,
+        Value::Date(_) | Value::DateTime(_) => 3,
+        Value::Text(_) => 4,
+        Value::Link(_) => 5,
+        Value::List(_) => 6,
+    };
+    let text = |x: &str, y: &str| {
+        x.to_lowercase()
+            .cmp(&y.to_lowercase())
+            .then_with(|| x.cmp(y))
+    };
+    rank(a).cmp(&rank(b)).then_with(|| match (a, b) {
+        (Value::Bool(x), Value::Bool(y)) => x.cmp(y),
+        (Value::Number(x), Value::Number(y)) => x.total_cmp(y),
+        (Value::Date(_) | Value::DateTime(_), Value::Date(_) | Value::DateTime(_)) => {
+            datetime(a).cmp(&datetime(b))
+        }
+        (Value::Text(x), Value::Text(y)) => text(x, y),
+        (Value::Link(x), Value::Link(y)) => text(&x.target, &y.target),
+        (Value::List(x), Value::List(y)) => x
+            .iter()
+            .zip(y)
+            .map(|(i, j)| sort_order(i, j))
+            .find(|o| o.is_ne())
+            .unwrap_or_else(|| x.len().cmp(&y.len())),
+        _ => Ordering::Equal,
+    })
+}
+
+/// Sort by keys that are read once per item rather than on every comparison,
+/// each key from low to high or, when `descending`, from high to low. The
+/// sort is stable, so items with equal keys keep their order.
+pub fn sort_by_keys<T: Copy>(items: &mut [T], descending: &[bool], keys: impl Fn(T) -> Vec<Value>) {
+    let mut keyed: Vec<(Vec<Value>, T)> = items.iter().map(|&i| (keys(i), i)).collect();
+    keyed.sort_by(|(a, _), (b, _)| {
+        a.iter()
+            .zip(b)
+            .zip(descending)
+            .map(|((x, y), descending)| match descending {
+                true => sort_order(y, x),
+                false => sort_order(x, y),
+            })
+            .find(|o| o.is_ne())
+            .unwrap_or(Ordering::Equal)
+    });
+    for (slot, (_, item)) in items.iter_mut().zip(keyed) {
+        *slot = item;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::NaiveDate;
+
+    fn mixed() -> Vec<Value> {
+        let date = |d| Value::Date(NaiveDate::parse_from_str(d, "%Y-%m-%d").unwrap());
+        vec![
+            Value::Link(Link::wiki("Beta")),
+            Value::Text("beta".into()),
+            Value::Link(Link::wiki("alpha")),
+            Value::Text("Alpha".into()),
+            Value::Text("2024-01-02".into()),
+            date("2024-01-03"),
+            Value::Number(3.0),
+            Value::Null,
+            Value::Link(Link::wiki("ALPHA")),
+            Value::Text("alpha".into()),
+            date("2023-12-31"),
+            Value::Number(-1.5),
+            Value::Bool(true),
+            Value::List(vec![Value::Number(1.0)]),
+        ]
+    }
+
+    #[test]
+    fn sort_order_is_total() {
+        let values = mixed();
+        for a in &values {
+            assert_eq!(sort_order(a, a), Ordering::Equal);
+            for b in &values {
+                assert_eq!(sort_order(a, b), sort_order(b, a).reverse());
+                for c in &values {
+                    if sort_order(a, b).is_le() && sort_order(b, c).is_le() {
+                        assert!(sort_order(a, c).is_le(), "{:?} {:?} {:?}", a, b, c);
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn sort_mixed_values() {
+        // Enough values that the standard sort checks its comparator
+        let mut values: Vec<Value> = (0..40).flat_map(|_| mixed()).collect();
+        values.sort_by(sort_order);
+        let shown: Vec<String> = values.iter().step_by(40).map(|v| v.to_string()).collect();
+        assert_eq!(
+            shown,
+            [
+                "",
+                "true",
+                "-1.5",
+                "3",
+                "2023-12-31",
+                "2024-01-03",
+                "2024-01-02",
+                "Alpha",
+                "alpha",
+                "beta",
+                "[[ALPHA]]",
+                "[[alpha]]",
+                "[[Beta]]",
+                "1"
+            ]
+        );
+    }
+
+    fn note(path: &str, tags: &[&str], fields: &[(&str, Value)]) -> FileData {
+        FileData {
+            path: path.into(),
+            hashtags: tags.iter().map(|t| t.to_string()).collect(),
+            metadata: fields
+                .iter()
+                .map(|(k, v)| (k.to_string(), v.clone()))
+                .collect(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn parse_table_query() {
+        let query = parse(
+            r#"table without id file.name as "Name", due FROM #proj and -"Archive"
+            WHERE due < date("2024-03-02") SORT due DESC, file.name LIMIT 5 WHERE !done"#,
+        )
+        .unwrap();
+        let QueryKind::Table {
+            columns,
+            without_id,
+        } = &query.kind
+        else {
+            panic!("not a table");
+        };
+        let names: Vec<&str> = columns.iter().map(|(_, n)| n.as_str()).collect();
+        assert_eq!(names, ["Name", "due"]);
+        assert!(*without_id);
+        let descending: Vec<bool> = query.sort.iter().map(|(_, d)| *d).collect();
+        assert_eq!(descending, [true, false]);
+        assert_eq!(query.limit, Some(5));
+
+        let due = |d: &str| ("due", Value::Date(parse_date_arg(d).unwrap()));
+        let open = note("Projects/a.md", &["#proj/web"], &[due("2024-03-01")]);
+        let done = note(
+            "Projects/b.md",
+            &["#proj"],
+            &[due("2024-03-01"), ("done", Value::Bool(true))],
+        );
+        let late = note("Projects/c.md", &["#proj"], &[due("2024-03-02")]);
+        let archived = note("Archive/d.md", &["#proj"], &[due("2024-01-01")]);
+        let other = note("e.md", &["#projects"], &[due("2024-01-01")]);
+        let from = query.from.as_ref().unwrap();
+        let filter = query.filter.as_ref().unwrap();
+        let found: Vec<&str> = [&open, &done, &late, &archived, &other]
+            .into_iter()
+            .filter(|n| from.matches(n) && filter.test(n))
+            .map(|n| n.path.to_str().unwrap())
+            .collect();
+        assert_eq!(found, ["Projects/a.md"]);
+    }
+
+    #[test]
+    fn parse_expressions() {
+        let eval = |expr: &str, note: &FileData| match parse(&format!("LIST {}", expr)) {
+            Ok(Query {
+                kind: QueryKind::List(Some(e)),
+                ..
+            }) => e.eval(note),
+            _ => panic!("`{}` did not parse", expr),
+        };
+        let n = note(
+            "Books/Tenggelamnya.md",
+            &[],
+            &[
+                ("Rating", Value::Number(9.0)),
+                ("author", Value::Link(Link::wiki("Hamka"))),
+            ],
+        );
+        assert_eq!(eval("rating >= 9 and !(rating > 9)", &n), Value::Bool(true));
+        assert_eq!(
+            eval("rating = -1 or author = [[hamka]]", &n),
+            Value::Bool(true)
+        );
+        assert_eq!(
+            eval(r#"contains(file.folder, "Books")"#, &n),
+            Value::Bool(true)
+        );
+        assert_eq!(eval("default(missing, 3)", &n), Value::Number(3.0));
+        assert_eq!(eval(r#"upper("a\"b")"#, &n), Value::Text("A\"B".into()));
+        assert_eq!(eval("length(file.tags)", &n), Value::Number(0.0));
+    }
+
+    #[test]
+    fn parse_errors() {
+        let error = |q: &str| parse(q).err().map(|e| e.to_string());
+        assert_eq!(
+            error(r#"LIST WHERE status = "open"#).as_deref(),
+            Some("unterminated string at position 21")
+        );
+        assert_eq!(
+            error("TABLE WITHOUT file.name").as_deref(),
+            Some("expected `ID` after `WITHOUT`, found `file.name` at position 15")
+        );
+        assert_eq!(
+            error("LIST GROUP BY status").as_deref(),
+            Some("only `WHERE`, `SORT` and `LIMIT` are supported at position 6")
+        );
+        assert_eq!(
+            error("LIST LIMIT").as_deref(),
+            Some("expected a number after `LIMIT`, found the end of the query at position 11")
+        );
+        assert_eq!(
+            error("LIST FROM #a and").as_deref(),
+            Some("expected `#tag` or `\"folder\"`, found the end of the query at position 17")
+        );
+    }
+}
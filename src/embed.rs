@@ -0,0 +1,405 @@
+use crate::document::{
+    self, BlockElement as Block, DocumentAST, HTMLItem, InlineElement as Inline,
+};
+use crate::frontmatter;
+use crate::links::Link;
+use crate::resolve::{Resolution, Resolver};
+use crate::value::Metadata;
+use crate::{vault, Vault};
+use colored::Colorize;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// How many embeds deep notes are expanded unless told otherwise
+pub const MAX_DEPTH: usize = 4;
+
+/// A note, heading or block that is being embedded
+type Source = (PathBuf, Option<String>, Option<String>);
+
+/// Replaces `![[embeds]]` of notes with the note, section or block they
+/// point to, as Obsidian shows them.
+///
+/// Embedded content comes as a `Div` with the class `embed`, a `source`
+/// attribute naming where it comes from and a `note` attribute with the path
+/// of that note, which links inside it are relative to. Embeds inside it are expanded in
+/// turn, up to a maximum depth, and an embed of something it is already
+/// inside of is left as it is. Embeds of attachments are left for the
+/// writers, as are embeds standing inside formatting, links or tables.
+pub struct Transclusion<'a> {
+    root: &'a Path,
+    resolver: Resolver,
+    max_depth: usize,
+}
+
+impl<'a> Transclusion<'a> {
+    pub fn new(vault: &Vault, root: &'a Path, max_depth: usize) -> Self {
+        Self {
+            root,
+            resolver: vault.resolver(),
+            max_depth,
+        }
+    }
+
+    /// Expand the embeds in the blocks of the note at `path`.
+    pub fn expand(&self, blocks: Vec<Block>, path: &Path) -> Vec<Block> {
+        let mut inside = vec![(path.to_path_buf(), None, None)];
+        self.blocks(blocks, path, &mut inside)
+    }
+
+    fn blocks(&self, blocks: Vec<Block>, from: &Path, inside: &mut Vec<Source>) -> Vec<Block> {
+        let mut expanded = Vec::new();
+        for block in blocks {
+            match block {
+                Block::Para(inlines) => {
+                    expanded.extend(self.split(inlines, Block::Para, from, inside))
+                }
+                Block::Plain(inlines) => {
+                    expanded.extend(self.split(inlines, Block::Plain, from, inside))
+                }
+                Block::BlockQuote(b) => {
+                    expanded.push(Block::BlockQuote(self.blocks(b, from, inside)))
+                }
+                Block::BulletList(items) => {
+                    expanded.push(Block::BulletList(self.items(items, from, inside)))
+                }
+                Block::OrderedList(start, items) => {
+                    expanded.push(Block::OrderedList(start, self.items(items, from, inside)))
+                }
+                Block::Callout(callout) => {
+                    let content = self.blocks(callout.content, from, inside);
+                    expanded.push(Block::Callout(document::Callout { content, ..callout }))
+                }
+                Block::Div(item) => {
+                    let children = self.blocks(item.children, from, inside);
+                    expanded.push(Block::Div(HTMLItem { children, ..item }))
+                }
+                other => expanded.push(other),
+            }
+        }
+        expanded
+    }
+
+    fn items(
+        &self,
+        items: Vec<Vec<Block>>,
+        from: &Path,
+        inside: &mut Vec<Source>,
+    ) -> Vec<Vec<Block>> {
+        items
+            .into_iter()
+            .map(|item| self.blocks(item, from, inside))
+            .collect()
+    }
+
+    /// Break a paragraph around the embeds in it, as embedded notes are
+    /// blocks of their own.
+    fn split(
+        &self,
+        inlines: Vec<Inline>,
+        wrap: fn(Vec<Inline>) -> Block,
+        from: &Path,
+        inside: &mut Vec<Source>,
+    ) -> Vec<Block> {
+        let mut blocks = Vec::new();
+        let mut run = Vec::new();
+        for inline in inlines {
+            let embedded = match &inline {
+                Inline::Embed(link) => self.embed(link, from, inside),
+                _ => None,
+            };
+            match embedded {
+                Some(div) => {
+                    let text = document::tidy(std::mem::take(&mut run));
+                    if !text.is_empty() {
+                        blocks.push(wrap(text));
+                    }
+                    blocks.push(div);
+                }
+                None => run.push(inline),
+            }
+        }
+        let text = document::tidy(run);
+        if !text.is_empty() || blocks.is_empty() {
+            blocks.push(wrap(text));
+        }
+        blocks
+    }
+
+    /// The embedded content of a link, or `None` to leave the link as it is.
+    fn embed(&self, link: &Link, from: &Path, inside: &mut Vec<Source>) -> Option<Block> {
+        let path = match self.resolver.resolve(link, from) {
+            Resolution::Resolved(path) if vault::is_note(&path) => path,
+            _ => return None,
+        };
+        let source: Source = (path.clone(), link.heading.clone(), link.block.clone());
+        if inside.contains(&source) {
+            eprintln!(
+                "{} {}: {} would embed a note inside itself, left as a link",
+                "warning:".yellow(),
+                from.display(),
+                link
+            );
+            return None;
+        }
+        if inside.len() > self.max_depth {
+            // Only worth a warning when embeds are expanded at all
+            if self.max_depth > 0 {
+                eprintln!(
+                    "{} {}: {} is more than {} embeds deep, left as a link",
+                    "warning:".yellow(),
+                    from.display(),
+                    link,
+                    self.max_depth
+                );
+            }
+            return None;
+        }
+        let content = match fs::read_to_string(self.root.join(&path)) {
+            Ok(content) => content,
+            Err(e) => {
+                eprintln!(
+                    "{} {}: {} could not be read, left as a link: {}",
+                    "warning:".yellow(),
+                    from.display(),
+                    path.display(),
+                    e
+                );
+                return None;
+            }
+        };
+        let (_, body) = frontmatter::split(&content);
+        let blocks = DocumentAST::parse(body, Metadata::new()).content;
+        let (part, name) = match (&link.heading, &link.block) {
+            (Some(heading), _) => (section(blocks, heading), format!("#{}", heading)),
+            (None, Some(id)) => (block_with_id(&blocks, id), format!("#^{}", id)),
+            (None, None) => (Some(blocks), String::new()),
+        };
+        let Some(part) = part else {
+            eprintln!(
+                "{} {}: {} has no {}, left as a link",
+                "warning:".yellow(),
+                from.display(),
+                path.display(),
+                name
+            );
+            return None;
+        };
+        inside.push(source);
+        let children = self.blocks(part, &path, inside);
+        inside.pop();
+        Some(Block::Div(HTMLItem {
+            classes: vec!["embed".into()],
+            attrs: vec![
+                (
+                    "source".into(),
+                    format!("{}{}", path.with_extension("").display(), name),
+                ),
+                ("note".into(), path.display().to_string()),
+            ],
+            ..HTMLItem::new(children)
+        }))
+    }
+}
+
+/// A heading and everything under it, up to the next heading of the same
+/// level or above. `A#B` is heading `B` under `A`, and only `B` is looked for.
+fn section(blocks: Vec<Block>, heading: &str) -> Option<Vec<Block>> {
+    let heading = heading.rsplit('#').next().unwrap_or(heading).trim();
+    let (start, level) = blocks.iter().enumerate().find_map(|(n, b)| match b {
+        Block::Header(level, item)
+            if document::stringify(&item.children).eq_ignore_ascii_case(heading)
+                || item.ident == document::ident(heading) =>
+        {
+            Some((n, *level))
+        }
+        _ => None,
+    })?;
+    let end = blocks[start + 1..]
+        .iter()
+        .position(|b| matches!(b, Block::Header(l, _) if *l <= level))
+        .map_or(blocks.len(), |n| start + 1 + n);
+    Some(blocks.into_iter().take(end).skip(start).collect())
+}
+
+/// The block marked `^id`: the paragraph it ends, the list item it ends the
+/// first line of, or the block before it when it stands alone.
+fn block_with_id(blocks: &[Block], id: &str) -> Option<Vec<Block>> {
+    let ends_with_id = |block: Option<&Block>| match block {
+        Some(Block::Para(i) | Block::Plain(i)) => {
+            matches!(i.last(), Some(Inline::BlockId(b)) if b == id)
+        }
+        _ => false,
+    };
+    for (n, block) in blocks.iter().enumerate() {
+        if ends_with_id(Some(block)) {
+            return match block {
+                Block::Para(i) | Block::Plain(i) if i.len() == 1 => {
+                    n.checked_sub(1).map(|p| vec![blocks[p].clone()])
+                }
+                _ => Some(vec![block.clone()]),
+            };
+        }
+        let found = match block {
+            Block::BulletList(items) | Block::OrderedList(_, items) => {
+                items
+                    .iter()
+                    .find_map(|item| match ends_with_id(item.first()) {
+                        true => Some(vec![match block {
+                            Block::OrderedList(start, _) => {
+                                Block::OrderedList(*start, vec![item.clone()])
+                            }
+                            _ => Block::BulletList(vec![item.clone()]),
+                        }]),
+                        false => block_with_id(item, id),
+                    })
+            }
+            Block::BlockQuote(b) => block_with_id(b, id),
+            Block::Callout(callout) => block_with_id(&callout.content, id),
+            Block::Div(item) => block_with_id(&item.children, id),
+            _ => None,
+        };
+        if found.is_some() {
+            return found;
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::FileData;
+
+    /// A vault with its notes written to a folder of its own
+    fn vault(name: &str, notes: &[(&str, &str)]) -> (PathBuf, Vault) {
+        let root = std::env::temp_dir().join(format!("obsmd-{}-{}", name, std::process::id()));
+        for (path, content) in notes {
+            let file = root.join(path);
+            fs::create_dir_all(file.parent().unwrap()).unwrap();
+            fs::write(file, content).unwrap();
+        }
+        let notes = notes
+            .iter()
+            .map(|(path, _)| FileData {
+                path: path.into(),
+                ..Default::default()
+            })
+            .collect();
+        (
+            root,
+            Vault {
+                notes,
+                attachments: Vec::new(),
+            },
+        )
+    }
+
+    fn expand(root: &Path, vault: &Vault, path: &str, max_depth: usize) -> String {
+        let content = fs::read_to_string(root.join(path)).unwrap();
+        let mut doc = DocumentAST::parse(&content, Metadata::new());
+        doc.content = Transclusion::new(vault, root, max_depth).expand(doc.content, path.as_ref());
+        doc.to_string()
+    }
+
+    fn blocks(body: &str) -> Vec<Block> {
+        DocumentAST::parse(body, Metadata::new()).content
+    }
+
+    #[test]
+    fn sections_end_at_the_next_heading_of_their_level() {
+        let note = blocks("# A\n\na\n\n## B\n\nb\n\n### C\n\nc\n\n## D\n\nd\n");
+        let outline = |part: Option<Vec<Block>>| {
+            DocumentAST {
+                content: part.unwrap(),
+                ..Default::default()
+            }
+            .to_string()
+        };
+        let b = "Header 2 (\"b\", [], []) [Str \"B\"]\nPara [Str \"b\"]\n\
+                 Header 3 (\"c\", [], []) [Str \"C\"]\nPara [Str \"c\"]\n";
+        assert_eq!(outline(section(note.clone(), "B")), b);
+        assert_eq!(outline(section(note.clone(), "A#b")), b);
+        assert_eq!(
+            section(note.clone(), "A").map(|s| s.len()),
+            Some(note.len())
+        );
+        assert!(section(note, "E").is_none());
+    }
+
+    #[test]
+    fn blocks_by_id() {
+        let note =
+            blocks("para ^p1\n\n- one\n- two ^li\n\n| a |\n|---|\n| 1 |\n\n^tbl\n\n> quoted ^q\n");
+        let found = |id: &str| {
+            block_with_id(&note, id).map(|part| {
+                DocumentAST {
+                    content: part,
+                    ..Default::default()
+                }
+                .to_string()
+            })
+        };
+        assert_eq!(
+            found("p1").as_deref(),
+            Some("Para [Str \"para\" Space BlockId \"p1\"]\n")
+        );
+        assert_eq!(
+            found("li").as_deref(),
+            Some("BulletList\n  - item 1\n    Plain [Str \"two\" Space BlockId \"li\"]\n")
+        );
+        assert_eq!(
+            found("tbl").as_deref(),
+            Some("Table [Default]\n  | [Str \"a\"]\n  | [Str \"1\"]\n")
+        );
+        assert_eq!(
+            found("q").as_deref(),
+            Some("Para [Str \"quoted\" Space BlockId \"q\"]\n")
+        );
+        assert_eq!(found("none"), None);
+    }
+
+    #[test]
+    fn embeds_of_a_note_inside_itself_stay_links() {
+        let (root, vault) = vault("cycle", &[("A.md", "![[B]]\n"), ("B.md", "b\n\n![[A]]\n")]);
+        assert_eq!(
+            expand(&root, &vault, "A.md", MAX_DEPTH),
+            "Div (\"\", [\"embed\"], [(\"source\", \"B\"), (\"note\", \"B.md\")])\n  \
+             Para [Str \"b\"]\n  Para [Embed ![[A]]]\n"
+        );
+        fs::remove_dir_all(root).unwrap();
+    }
+
+    #[test]
+    fn embeds_stop_at_the_depth_limit() {
+        let (root, vault) = vault(
+            "depth",
+            &[
+                ("N0.md", "![[N1]]\n"),
+                ("N1.md", "![[N2]]\n"),
+                ("N2.md", "end\n"),
+            ],
+        );
+        assert_eq!(
+            expand(&root, &vault, "N0.md", 1),
+            "Div (\"\", [\"embed\"], [(\"source\", \"N1\"), (\"note\", \"N1.md\")])\n  \
+             Para [Embed ![[N2]]]\n"
+        );
+        assert_eq!(expand(&root, &vault, "N0.md", 0), "Para [Embed ![[N1]]]\n");
+        assert!(expand(&root, &vault, "N0.md", 2).contains("Para [Str \"end\"]"));
+        fs::remove_dir_all(root).unwrap();
+    }
+
+    #[test]
+    fn unreadable_embeds_stay_links() {
+        let (root, mut vault) = vault("unreadable", &[("A.md", "![[Gone]] text\n")]);
+        vault.notes.push(FileData {
+            path: "Gone.md".into(),
+            ..Default::default()
+        });
+        assert_eq!(
+            expand(&root, &vault, "A.md", MAX_DEPTH),
+            "Para [Embed ![[Gone]] Space Str \"text\"]\n"
+        );
+        fs::remove_dir_all(root).unwrap();
+    }
+}
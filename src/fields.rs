@@ -0,0 +1,181 @@
+use lazy_static::lazy_static;
+use regex::Regex;
+
+lazy_static! {
+    /// List markers, task checkboxes and quote markers before a full-line field
+    static ref LINE_PREFIX_RE: Regex = Regex::new(
+        r"(?x)
+        ^\s*(?:>\s*)*                   # blockquotes and callouts
+        (?:(?:[-*+]|\d+[.)])\s+)?       # list marker
+        (?:\[.\]\s+)?                   # task checkbox
+        ",
+    )
+    .expect("Regex run error");
+    static ref MARKUP_RE: Regex = Regex::new(r"[*_~=`]").expect("Regex run error");
+}
+
+/// An inline Dataview field found on a line.
+#[derive(Debug, PartialEq)]
+pub struct Field<'a> {
+    /// The key with markdown formatting removed, e.g. `Due Date` for `**Due Date**`
+    pub key: String,
+    pub value: &'a str,
+}
+
+impl Field<'_> {
+    /// The key the way Dataview normalises it: lowercase, with spaces turned
+    /// into `-` and anything other than letters, digits, `-` and `_` dropped.
+    pub fn canonical_key(&self) -> String {
+        self.key
+            .trim()
+            .to_lowercase()
+            .split_whitespace()
+            .collect::<Vec<&str>>()
+            .join("-")
+            .chars()
+            .filter(|c| c.is_alphanumeric() || *c == '-' || *c == '_')
+            .collect()
+    }
+}
+
+/// Find the inline fields on one line of a note.
+///
+/// Dataview has three forms:
+/// - `key:: value` filling the whole line, also after a list marker or checkbox
+/// - `[key:: value]` anywhere in the text, any number per line
+/// - `(key:: value)`, the same but with the key hidden when rendered
+///
+/// `prose` is the same line with code blanked out (see `markdown::mask_code`),
+/// so fields inside code are skipped while values are read from `line`.
+pub fn inline_fields<'a>(prose: &str, line: &'a str) -> Vec<Field<'a>> {
+    let bracketed = bracketed_fields(prose, line);
+    if !bracketed.is_empty() {
+        return bracketed;
+    }
+    let start = LINE_PREFIX_RE.find(prose).map(|m| m.end()).unwrap_or(0);
+    match prose[start..].find("::") {
+        Some(at) => {
+            let key = &prose[start..start + at];
+            if key.trim().is_empty() || key.contains(['[', ']', '(', ')']) {
+                return Vec::new();
+            }
+            vec![Field {
+                key: strip_markup(key),
+                value: line[start + at + 2..].trim(),
+            }]
+        }
+        None => Vec::new(),
+    }
+}
+
+fn bracketed_fields<'a>(prose: &str, line: &'a str) -> Vec<Field<'a>> {
+    let mut fields = Vec::new();
+    let bytes = prose.as_bytes();
+    let mut i = 0;
+    while i < bytes.len() {
+        let close = match bytes[i] {
+            b'[' => b']',
+            b'(' => b')',
+            _ => {
+                i += 1;
+                continue;
+            }
+        };
+        let Some(end) = matching(bytes, i, bytes[i], close) else {
+            i += 1;
+            continue;
+        };
+        let inner = &prose[i + 1..end];
+        // `[[wikilinks]]` are never fields, but may hold one in their value
+        let field = inner
+            .find("::")
+            .filter(|_| !inner.starts_with('['))
+            .map(|at| (&inner[..at], i + 1 + at + 2));
+        match field {
+            Some((key, value_start)) if valid_key(key) => {
+                fields.push(Field {
+                    key: strip_markup(key),
+                    value: line[value_start..end].trim(),
+                });
+                i = end + 1;
+            }
+            _ => i += 1,
+        }
+    }
+    fields
+}
+
+/// Index of the bracket closing the one at `open_at`, counting nested pairs.
+fn matching(bytes: &[u8], open_at: usize, open: u8, close: u8) -> Option<usize> {
+    let mut depth = 0;
+    for (i, &b) in bytes.iter().enumerate().skip(open_at) {
+        if b == open {
+            depth += 1;
+        } else if b == close {
+            depth -= 1;
+            if depth == 0 {
+                return Some(i);
+            }
+        }
+    }
+    None
+}
+
+fn valid_key(key: &str) -> bool {
+    !key.trim().is_empty() && !key.contains(['[', ']', '(', ')', ':'])
+}
+
+fn strip_markup(key: &str) -> String {
+    MARKUP_RE.replace_all(key, "").trim().to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fields(line: &str) -> Vec<(String, &str)> {
+        inline_fields(line, line)
+            .into_iter()
+            .map(|f| (f.key, f.value))
+            .collect()
+    }
+
+    #[test]
+    fn full_line_fields() {
+        assert_eq!(fields("due:: 2024-03-01"), [("due".into(), "2024-03-01")]);
+        assert_eq!(
+            fields("- [ ] **Due Date**:: tomorrow"),
+            [("Due Date".into(), "tomorrow")]
+        );
+        assert_eq!(fields("no field here"), []);
+    }
+
+    #[test]
+    fn bracketed_fields_anywhere_on_the_line() {
+        assert_eq!(
+            fields("Read [rating:: 9] and (author:: [[Hamka]]) today"),
+            [("rating".into(), "9"), ("author".into(), "[[Hamka]]")]
+        );
+        assert_eq!(fields("a [[link]] and [not a field]"), []);
+    }
+
+    #[test]
+    fn fields_in_code_are_skipped() {
+        let line = "`[key:: value]` [real:: yes]";
+        let prose = crate::markdown::mask_code(line);
+        let found: Vec<(String, &str)> = inline_fields(&prose, line)
+            .into_iter()
+            .map(|f| (f.key, f.value))
+            .collect();
+        assert_eq!(found, [("real".into(), "yes")]);
+    }
+
+    #[test]
+    fn canonical_keys() {
+        let field = Field {
+            key: "Due Date!".into(),
+            value: "",
+        };
+        assert_eq!(field.canonical_key(), "due-date");
+    }
+}
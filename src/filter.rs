@@ -0,0 +1,155 @@
+use crate::date::parse_date_arg;
+use crate::dql;
+use crate::links::Link;
+use crate::resolve::Resolution;
+use crate::tags;
+use crate::value::Value;
+use crate::{FileData, Vault};
+use chrono::NaiveDate;
+use clap::Args;
+use regex::Regex;
+use std::cmp::Ordering;
+use std::fs;
+use std::io::{Error, ErrorKind};
+use std::path::Path;
+
+/// Filters on the notes of `list`. A note must pass all of them, or any of
+/// them with `--any`; repeated flags count as separate filters.
+#[derive(Args, Clone)]
+pub struct Filters {
+    /// Notes with this tag or a tag nested below it
+    #[arg(long)]
+    tag: Vec<String>,
+    /// Notes with this note among their parents
+    #[arg(long)]
+    parent: Vec<String>,
+    /// Notes dated on or after this date
+    #[arg(long, value_parser = parse_date_arg)]
+    since: Option<NaiveDate>,
+    /// Notes dated on or before this date
+    #[arg(long, value_parser = parse_date_arg)]
+    until: Option<NaiveDate>,
+    /// `key=value`, or only `key` for notes that have the field at all
+    #[arg(long)]
+    field: Vec<String>,
+    /// Notes with at least this many internal links
+    #[arg(long)]
+    min_links: Option<usize>,
+    /// Notes whose text matches this regex
+    #[arg(long)]
+    content: Option<String>,
+    /// Keep notes that pass any filter instead of all of them
+    #[arg(long)]
+    any: bool,
+}
+
+impl Filters {
+    /// The notes of the vault that pass the filters, in vault order.
+    ///
+    /// Note contents are only read for `--content`, and only for notes whose
+    /// other filters have not already decided the result.
+    pub fn apply<'a>(&self, vault: &'a Vault, root: &Path) -> Result<Vec<&'a FileData>, Error> {
+        let content = match &self.content {
+            Some(c) => Some(Regex::new(c).map_err(|e| Error::new(ErrorKind::InvalidInput, e))?),
+            None => None,
+        };
+        let resolver = vault.resolver();
+        // Parents are compared by the note they resolve to, or by name when they do not resolve
+        let parents: Vec<(String, Option<std::path::PathBuf>)> = self
+            .parent
+            .iter()
+            .map(|p| match resolver.resolve(&Link::wiki(p), Path::new("")) {
+                Resolution::Resolved(path) => (p.to_lowercase(), Some(path)),
+                _ => (p.to_lowercase(), None),
+            })
+            .collect();
+        let mut notes = Vec::new();
+        for note in &vault.notes {
+            let mut checks: Vec<bool> = Vec::new();
+            checks.extend(
+                self.tag
+                    .iter()
+                    .map(|t| note.hashtags.iter().any(|h| tags::is_within(h, t))),
+            );
+            checks.extend(parents.iter().map(|(name, path)| {
+                note.parents.iter().any(|p| match path {
+                    Some(path) => {
+                        resolver.resolve(&Link::wiki(p), &note.path)
+                            == Resolution::Resolved(path.clone())
+                    }
+                    None => p.to_lowercase() == *name,
+                })
+            }));
+            let dated = note.date_source.is_some();
+            checks.extend(self.since.map(|d| dated && note.date >= d));
+            checks.extend(self.until.map(|d| dated && note.date <= d));
+            checks.extend(self.field.iter().map(|f| has_field(note, f)));
+            checks.extend(
+                self.min_links
+                    .map(|n| note.links.iter().filter(|l| l.is_internal()).count() >= n),
+            );
+            let decided = match self.any {
+                true => checks.iter().any(|&c| c),
+                false => checks.iter().any(|&c| !c),
+            };
+            let pass = match (&content, decided) {
+                (Some(re), false) => {
+                    let text = fs::read_to_string(root.join(&note.path))?;
+                    checks.push(re.is_match(&text));
+                    self.combine(&checks)
+                }
+                _ => self.combine(&checks),
+            };
+            if pass {
+                notes.push(note);
+            }
+        }
+        Ok(notes)
+    }
+
+    /// The flags that give these filters, to repeat them in another command.
+    pub fn args(&self) -> Vec<String> {
+        let mut args = Vec::new();
+        args.extend(self.tag.iter().map(|t| format!("--tag \"{}\"", t)));
+        args.extend(self.parent.iter().map(|p| format!("--parent \"{}\"", p)));
+        args.extend(self.since.map(|d| format!("--since {}", d)));
+        args.extend(self.until.map(|d| format!("--until {}", d)));
+        args.extend(self.field.iter().map(|f| format!("--field \"{}\"", f)));
+        args.extend(self.min_links.map(|n| format!("--min-links {}", n)));
+        args.extend(self.content.iter().map(|c| format!("--content \"{}\"", c)));
+        if self.any {
+            args.push("--any".into());
+        }
+        args
+    }
+
+    fn combine(&self, checks: &[bool]) -> bool {
+        match self.any {
+            // With no filters at all every note is kept
+            true => checks.is_empty() || checks.iter().any(|&c| c),
+            false => checks.iter().all(|&c| c),
+        }
+    }
+}
+
+/// Whether the note has the field of `key=value`, with that value if given.
+///
+/// The value is typed like a field value, so `due=2024-03-01` also matches
+/// `due:: March 1, 2024`, and a list field matches when any of its items does.
+fn has_field(note: &FileData, filter: &str) -> bool {
+    let (key, expected) = match filter.split_once('=') {
+        Some((k, v)) => (k.trim(), Some(Value::parse(v))),
+        None => (filter.trim(), None),
+    };
+    let value = dql::field(note, key);
+    match expected {
+        None => value != Value::Null,
+        Some(expected) => {
+            dql::compare(&value, &expected) == Some(Ordering::Equal)
+                || value
+                    .items()
+                    .iter()
+                    .any(|v| dql::compare(v, &expected) == Some(Ordering::Equal))
+        }
+    }
+}
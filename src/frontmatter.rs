@@ -0,0 +1,133 @@
+use crate::value::{Metadata, Value};
+use serde_yaml::Value as Yaml;
+
+/// Split a note into its YAML frontmatter and the remaining body.
+///
+/// Frontmatter only counts when the very first line of the note is `---`
+/// and a closing `---` (or `...`) line follows. A byte order mark before it,
+/// as some Windows editors write, is skipped.
+pub fn split(content: &str) -> (Option<&str>, &str) {
+    let start = content.strip_prefix('\u{feff}').unwrap_or(content);
+    let Some(rest) = start
+        .strip_prefix("---\n")
+        .or_else(|| start.strip_prefix("---\r\n"))
+    else {
+        return (None, content);
+    };
+    let mut offset = 0;
+    for line in rest.split_inclusive('\n') {
+        let trimmed = line.trim_end();
+        if trimmed == "---" || trimmed == "..." {
+            return (Some(&rest[..offset]), &rest[offset + line.len()..]);
+        }
+        offset += line.len();
+    }
+    (None, content)
+}
+
+/// Parse YAML frontmatter into the same [Metadata] that `extract_keywords` builds.
+///
+/// - Strings are typed the same way inline values are, so `date: 2024-03-01`
+///   becomes a date and `up: "[[Index]]"` a link.
+/// - Nested maps are flattened into dotted keys, e.g. `project.status`.
+/// - `tags` (and `tag`) values are given a leading `#` like inline hashtags.
+pub fn parse(yaml: &str) -> Result<Metadata, serde_yaml::Error> {
+    let mut metadata = Metadata::new();
+    if yaml.trim().is_empty() {
+        return Ok(metadata);
+    }
+    if let Yaml::Mapping(map) = serde_yaml::from_str::<Yaml>(yaml)? {
+        for (key, value) in map {
+            if let Some(key) = key_name(&key) {
+                flatten(&key, value, &mut metadata);
+            }
+        }
+    }
+    Ok(metadata)
+}
+
+fn flatten(key: &str, value: Yaml, metadata: &mut Metadata) {
+    match value {
+        Yaml::Mapping(map) => {
+            for (k, v) in map {
+                if let Some(k) = key_name(&k) {
+                    flatten(&format!("{}.{}", key, k), v, metadata);
+                }
+            }
+        }
+        Yaml::Tagged(tagged) => flatten(key, tagged.value, metadata),
+        value if key == "tags" || key == "tag" => {
+            // Obsidian allows `tags: a, b` as well as a proper list
+            let tags: Vec<Value> = convert(value)
+                .items()
+                .iter()
+                .flat_map(|tag| {
+                    tag.to_string()
+                        .split(',')
+                        .map(String::from)
+                        .collect::<Vec<_>>()
+                })
+                .map(|t| t.trim().trim_start_matches('#').to_string())
+                .filter(|t| !t.is_empty())
+                .map(|t| Value::Text(format!("#{}", t)))
+                .collect();
+            metadata.insert(key.into(), Value::List(tags));
+        }
+        value => {
+            metadata.insert(key.into(), convert(value));
+        }
+    }
+}
+
+fn convert(value: Yaml) -> Value {
+    match value {
+        Yaml::Null => Value::Null,
+        Yaml::Bool(b) => Value::Bool(b),
+        Yaml::Number(n) => n.as_f64().map(Value::Number).unwrap_or(Value::Null),
+        Yaml::String(s) => Value::parse(&s),
+        Yaml::Sequence(seq) => Value::List(seq.into_iter().map(convert).collect()),
+        Yaml::Tagged(tagged) => convert(tagged.value),
+        // Maps inside lists have no dotted key to go under
+        Yaml::Mapping(_) => Value::Null,
+    }
+}
+
+fn key_name(key: &Yaml) -> Option<String> {
+    match key {
+        Yaml::String(s) => Some(s.clone()),
+        Yaml::Number(n) => Some(n.to_string()),
+        Yaml::Bool(b) => Some(b.to_string()),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn split_frontmatter() {
+        assert_eq!(
+            split("---\ndate: 2024-03-01\n---\nbody\n"),
+            (Some("date: 2024-03-01\n"), "body\n")
+        );
+        assert_eq!(
+            split("---\r\na: 1\r\n...\r\nbody"),
+            (Some("a: 1\r\n"), "body")
+        );
+        assert_eq!(split("---\nnever closed\n"), (None, "---\nnever closed\n"));
+        assert_eq!(
+            split("text\n---\na: 1\n---\n"),
+            (None, "text\n---\na: 1\n---\n")
+        );
+    }
+
+    #[test]
+    fn split_after_byte_order_mark() {
+        assert_eq!(
+            split("\u{feff}---\na: 1\n---\nbody"),
+            (Some("a: 1\n"), "body")
+        );
+        assert_eq!(split("\u{feff}body"), (None, "\u{feff}body"));
+    }
+}
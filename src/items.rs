@@ -0,0 +1,51 @@
+use crate::links::Link;
+use crate::value::Metadata;
+use lazy_static::lazy_static;
+use regex::Regex;
+
+lazy_static! {
+    static ref LIST_ITEM_RE: Regex = Regex::new(
+        r"(?x)
+        ^(?P<indent>\s*)(?:>\s*)*
+        (?:[-*+]|\d+[.)])\s+            # list marker
+        (?:\[(?P<status>.)\]\s+)?       # task checkbox
+        (?P<text>.*)$
+        ",
+    )
+    .expect("Regex run error");
+}
+
+/// A list item or task, with the metadata written on its own line.
+///
+/// Inline fields on the line belong to the item only, while its tags and links
+/// also count for the note.
+#[derive(Clone, Debug, Default)]
+pub struct ListItem {
+    /// Line of the note the item is on, starting at 1
+    pub line: usize,
+    /// Columns of indentation, to tell nested items apart
+    pub indent: usize,
+    /// The character between the checkbox brackets, `None` for a plain list item
+    pub status: Option<char>,
+    /// The item without its list marker and checkbox
+    pub text: String,
+    pub fields: Metadata,
+    pub tags: Vec<String>,
+    pub links: Vec<Link>,
+}
+
+impl ListItem {
+    /// Start an item if `prose` is a list item line. `line` is the text of the
+    /// same line with code left in, see `markdown::mask_code`.
+    pub fn parse(prose: &str, line: &str, number: usize) -> Option<Self> {
+        let c = LIST_ITEM_RE.captures(prose)?;
+        let text = c.name("text")?;
+        Some(Self {
+            line: number,
+            indent: c["indent"].replace('\t', "    ").len(),
+            status: c.name("status").and_then(|s| s.as_str().chars().next()),
+            text: line[text.start()..].trim_end().into(),
+            ..Default::default()
+        })
+    }
+}
@@ -0,0 +1,256 @@
+use lazy_static::lazy_static;
+use regex::{Captures, Regex};
+use std::fmt::{Display, Formatter};
+use std::ops::Range;
+
+lazy_static! {
+    static ref WIKILINK_RE: Regex =
+        Regex::new(r"(?P<embed>!?)\[\[(?P<inner>[^\[\]]+?)\]\]").expect("Regex run error");
+    static ref MDLINK_RE: Regex = Regex::new(
+        r#"(?x)
+        (?P<embed>!?)
+        \[(?P<text>[^\[\]]*)\]
+        \(\s*<?(?P<url>[^()\s<>]+)>?(?:\s+"[^"]*")?\s*\)  # `(url "title")`
+        "#,
+    )
+    .expect("Regex run error");
+    static ref URL_RE: Regex =
+        Regex::new(r#"<?(?P<url>[a-zA-Z][a-zA-Z0-9+.-]*://[^\s<>()\[\]]+)>?"#)
+            .expect("Regex run error");
+    static ref SCHEME_RE: Regex =
+        Regex::new(r"^[a-zA-Z][a-zA-Z0-9+.-]*:").expect("Regex run error");
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum LinkKind {
+    /// `[[Note]]`
+    #[default]
+    Wiki,
+    /// `[text](Note.md)`
+    Markdown,
+    /// `[text](https://...)` or a bare `https://...`
+    External,
+}
+
+/// A link from a note, with its target split into parts the way Obsidian reads it.
+///
+/// `[[Note#Heading|alias]]` has target `Note`, heading `Heading` and alias `alias`.
+/// `![[Note#^block-id]]` is an embed with target `Note` and block `block-id`.
+/// A link inside the same note such as `[[#Heading]]` has an empty target.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Link {
+    pub kind: LinkKind,
+    pub target: String,
+    pub heading: Option<String>,
+    pub block: Option<String>,
+    /// The text after `|`, or the text of a markdown link
+    pub alias: Option<String>,
+    pub embed: bool,
+    /// Line of the note the link is on, starting at 1
+    pub line: usize,
+}
+
+impl Link {
+    /// Parse the inside of `[[...]]`.
+    pub fn wiki(inner: &str) -> Self {
+        // In tables the alias pipe is escaped as `\|`
+        let inner = inner.replace("\\|", "|");
+        let (reference, alias) = match inner.split_once('|') {
+            Some((r, a)) => (r, Some(a.trim().to_string())),
+            None => (inner.as_str(), None),
+        };
+        Self {
+            alias,
+            ..Self::reference(LinkKind::Wiki, reference)
+        }
+    }
+
+    /// Parse the URL of a markdown link to a note or file, e.g. `My%20Note.md#Heading`.
+    pub fn markdown(url: &str) -> Self {
+        Self::reference(LinkKind::Markdown, &url.replace("%20", " "))
+    }
+
+    /// Split `Note#Heading` or `Note#^block` into its parts.
+    fn reference(kind: LinkKind, reference: &str) -> Self {
+        let (target, subpath) = match reference.split_once('#') {
+            Some((t, s)) => (t, Some(s.trim())),
+            None => (reference, None),
+        };
+        let (heading, block) = match subpath {
+            Some(s) => match s.strip_prefix('^') {
+                Some(b) => (None, Some(b.to_string())),
+                None => (Some(s.to_string()), None),
+            },
+            None => (None, None),
+        };
+        Self {
+            kind,
+            target: target.trim().into(),
+            heading,
+            block,
+            ..Default::default()
+        }
+    }
+
+    pub fn is_internal(&self) -> bool {
+        self.kind != LinkKind::External
+    }
+}
+
+impl Display for Link {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let mut reference = self.target.clone();
+        if let Some(h) = &self.heading {
+            reference = format!("{}#{}", reference, h);
+        }
+        if let Some(b) = &self.block {
+            reference = format!("{}#^{}", reference, b);
+        }
+        let embed = if self.embed { "!" } else { "" };
+        match (self.kind, &self.alias) {
+            (LinkKind::Wiki, Some(a)) => write!(f, "{}[[{}|{}]]", embed, reference, a),
+            (LinkKind::Wiki, None) => write!(f, "{}[[{}]]", embed, reference),
+            (_, alias) => write!(
+                f,
+                "{}[{}]({})",
+                embed,
+                alias.as_deref().unwrap_or_default(),
+                reference
+            ),
+        }
+    }
+}
+
+/// Find every link on one line of a note.
+///
+/// Links are looked for in `prose`, the line with its code blanked out by
+/// [crate::markdown::mask_code], and their text is taken from `line`.
+pub fn extract(prose: &str, line: &str, line_number: usize) -> Vec<Link> {
+    // The masked line has the same length, so a match in it is a range of the line
+    let matches = |re: &'static Regex| {
+        re.find_iter(prose)
+            .filter_map(|m| Some((m.range(), re.captures(line.get(m.range())?)?)))
+            .collect::<Vec<(Range<usize>, Captures)>>()
+    };
+    let mut links: Vec<(usize, Link)> = Vec::new();
+    let mut taken: Vec<Range<usize>> = Vec::new();
+    for (range, c) in matches(&WIKILINK_RE) {
+        taken.push(range.clone());
+        links.push((
+            range.start,
+            Link {
+                embed: !c["embed"].is_empty(),
+                line: line_number,
+                ..Link::wiki(&c["inner"])
+            },
+        ));
+    }
+    for (range, c) in matches(&MDLINK_RE) {
+        if overlaps(&taken, &range) {
+            continue;
+        }
+        taken.push(range.clone());
+        let url = &c["url"];
+        let text = Some(c["text"].to_string()).filter(|t| !t.is_empty());
+        let link = if SCHEME_RE.is_match(url) {
+            Link {
+                kind: LinkKind::External,
+                target: url.into(),
+                ..Default::default()
+            }
+        } else {
+            Link::markdown(url)
+        };
+        links.push((
+            range.start,
+            Link {
+                alias: text,
+                embed: !c["embed"].is_empty(),
+                line: line_number,
+                ..link
+            },
+        ));
+    }
+    for (range, c) in matches(&URL_RE) {
+        if overlaps(&taken, &range) {
+            continue;
+        }
+        links.push((
+            range.start,
+            Link {
+                kind: LinkKind::External,
+                target: c["url"].into(),
+                line: line_number,
+                ..Default::default()
+            },
+        ));
+    }
+    links.sort_by_key(|(start, _)| *start);
+    links.into_iter().map(|(_, l)| l).collect()
+}
+
+fn overlaps(taken: &[Range<usize>], range: &Range<usize>) -> bool {
+    taken
+        .iter()
+        .any(|t| t.start < range.end && range.start < t.end)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn links(line: &str) -> Vec<Link> {
+        extract(&crate::markdown::mask_code(line), line, 1)
+    }
+
+    #[test]
+    fn wikilinks_are_split() {
+        let link = Link::wiki("Note#Heading|alias");
+        assert_eq!(link.target, "Note");
+        assert_eq!(link.heading.as_deref(), Some("Heading"));
+        assert_eq!(link.alias.as_deref(), Some("alias"));
+        let link = Link::wiki("Note#^block-id");
+        assert_eq!(
+            (link.heading, link.block.as_deref()),
+            (None, Some("block-id"))
+        );
+        let link = Link::wiki("#Heading");
+        assert_eq!(
+            (link.target.as_str(), link.heading.as_deref()),
+            ("", Some("Heading"))
+        );
+        assert_eq!(Link::wiki(r"Note\|alias").alias.as_deref(), Some("alias"));
+    }
+
+    #[test]
+    fn links_in_order() {
+        let found = links("![[Pic.png]] see [doc](My%20Note.md#Part) at https://x.org/a and [[B]]");
+        let shown: Vec<String> = found.iter().map(|l| l.to_string()).collect();
+        assert_eq!(
+            shown,
+            [
+                "![[Pic.png]]",
+                "[doc](My Note.md#Part)",
+                "[](https://x.org/a)",
+                "[[B]]"
+            ]
+        );
+        assert_eq!(found[1].kind, LinkKind::Markdown);
+        assert_eq!(found[2].kind, LinkKind::External);
+        assert!(found.iter().all(|l| l.line == 1));
+    }
+
+    #[test]
+    fn urls_inside_links_are_not_counted_twice() {
+        let found = links("[site](https://x.org/page) <https://y.org>");
+        let targets: Vec<&str> = found.iter().map(|l| l.target.as_str()).collect();
+        assert_eq!(targets, ["https://x.org/page", "https://y.org"]);
+    }
+
+    #[test]
+    fn links_in_code_are_skipped() {
+        let found = links("`[[Missing]]` but [[Real]]");
+        let targets: Vec<&str> = found.iter().map(|l| l.target.as_str()).collect();
+        assert_eq!(targets, ["Real"]);
+    }
+}
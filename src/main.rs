@@ -12,128 +12,142 @@ use std::path::{Path, PathBuf};
 use std::string::ParseError;
 use std::{default, fs, io::Write, str::FromStr};
 
-lazy_static! {
-    static ref DATE_RE: Regex = Regex::new(
-        r"(?x)
-        (?P<year>\d{4})  # the year
-        -
-        (?P<month>\d{2}) # the month
-        -
-        (?P<day>\d{2})   # the day
-        ",
-    )
-    .expect("Regex run error");
-}
-
-struct Date(chrono::NaiveDate);
-
-impl Default for Date {
-    fn default() -> Self {
-        Self(Default::default())
-    }
-}
-
-struct List(Vec<String>);
-
-struct Count(usize);
-
-impl Into<usize> for Count {
-    fn into(self) -> usize {
-        self.0
-    }
-}
-
-impl From<String> for Count {
-    fn from(value: String) -> Self {
-        Self(value.len())
-    }
-}
-
-impl Default for Count {
-    fn default() -> Self {
-        Self(Default::default())
-    }
-}
-
-impl Default for List {
-    fn default() -> Self {
-        Self(Default::default())
-    }
-}
-
-impl From<String> for List {
-    fn from(value: String) -> Self {
-        let mut data = Vec::new();
-        for v in value.split("::") {
-            data.push(v.trim().into())
-        }
-        Self(data)
-    }
-}
-
-impl From<String> for Date {
-    fn from(value: String) -> Self {
-        if DATE_RE.captures(&value).is_some() {
-            Self(chrono::NaiveDate::from_str(&value).expect(""))
-        } else {
-            Self(chrono::NaiveDate::from_str("").expect(""))
-        }
-    }
-}
-
-impl Into<chrono::NaiveDate> for Date {
-    fn into(self) -> chrono::NaiveDate {
-        self.0
-    }
-}
-impl Into<Vec<String>> for List {
-    fn into(self) -> Vec<String> {
-        self.0
-    }
-}
+use config::Config;
+use date::{DateResolver, DateSource};
+use items::ListItem;
+use links::Link;
+use resolve::{Resolution, Resolver};
+use value::{Metadata, Value, ValueError};
+
+mod backlinks;
+mod check;
+mod config;
+mod convert;
+mod date;
+mod document;
+mod dql;
+mod embed;
+mod fields;
+mod filter;
+mod frontmatter;
+mod items;
+mod links;
+mod markdown;
+mod native;
+mod output;
+mod pandoc;
+mod query;
+mod resolve;
+mod sort;
+mod tags;
+mod tasks;
+mod terminal;
+mod tree;
+mod value;
+mod vault;
 
 trait Extract {
-    fn read<T>(&self, key: &str) -> Option<T>
+    fn read<T>(&self, key: &str) -> Option<Result<T, ValueError>>
     where
-        T: From<String>;
+        T: for<'a> TryFrom<&'a Value, Error = ValueError>;
 }
 
-impl Extract for HashMap<String, String> {
-    fn read<T>(&self, key: &str) -> Option<T>
+impl Extract for Metadata {
+    fn read<T>(&self, key: &str) -> Option<Result<T, ValueError>>
     where
-        T: From<String>,
+        T: for<'a> TryFrom<&'a Value, Error = ValueError>,
     {
-        match self.get(key) {
-            Some(v) => Some(T::from(v.trim().to_string().clone())),
-            None => None,
-        }
+        self.get(key).map(T::try_from)
     }
 }
 
 #[derive(Clone, Default, Debug)]
 struct FileData {
     date: chrono::NaiveDate,
+    /// `None` when no source in the date fallback chain had a date
+    date_source: Option<DateSource>,
     parents: Vec<String>,
     hashtags: Vec<String>,
-    links: usize,
+    aliases: Vec<String>,
+    /// Every link in the note, in the order they appear
+    links: Vec<Link>,
+    /// List items and tasks, each with its own fields
+    items: Vec<ListItem>,
+    /// Every field of the note, from frontmatter and inline fields
+    metadata: Metadata,
+    /// Last modification time of the file
+    mtime: Option<chrono::NaiveDateTime>,
+    /// Size of the file in bytes
+    size: u64,
     name: String,
+    /// Location of the note relative to the vault root
+    path: PathBuf,
 }
 
 impl FileData {
-    fn with_name(mut self, name: String) -> Self {
-        self.name = name;
-        self
-    }
-}
-
-impl From<HashMap<String, String>> for FileData {
-    fn from(value: HashMap<String, String>) -> Self {
+    /// Build the note from its metadata, using the field mapping of the config.
+    ///
+    /// Values that cannot be converted are reported as warnings and left out.
+    fn new(path: PathBuf, metadata: Metadata, config: &Config, dates: &DateResolver) -> Self {
+        let warn = |key: &str, e: ValueError| {
+            eprintln!(
+                "{} {}: `{}` {}",
+                "warning:".yellow(),
+                path.display(),
+                key,
+                e
+            );
+        };
+        let read_all = |keys: &Vec<String>| -> Vec<String> {
+            keys.iter()
+                .filter_map(|k| match metadata.read::<Vec<String>>(k)? {
+                    Ok(v) => Some(v),
+                    Err(e) => {
+                        warn(k, e);
+                        None
+                    }
+                })
+                .flatten()
+                .collect()
+        };
+        let field_date = config.fields.date.iter().find_map(|k| {
+            match metadata.read::<chrono::NaiveDate>(k)? {
+                Ok(d) => Some(d),
+                Err(e) => {
+                    warn(k, e);
+                    None
+                }
+            }
+        });
+        let (date, date_source) = match dates.resolve(&path, field_date) {
+            Some((d, source)) => (d, Some(source)),
+            None => (Default::default(), None),
+        };
+        let links = match metadata.read::<Vec<Link>>("links") {
+            Some(Ok(l)) => l,
+            Some(Err(e)) => {
+                warn("links", e);
+                Vec::new()
+            }
+            None => Vec::new(),
+        };
         Self {
-            date: value.read::<Date>("tarikh").unwrap_or_default().into(),
-            parents: value.read::<List>("idx-naik").unwrap_or_default().into(),
-            hashtags: value.read::<List>("hashtag").unwrap_or_default().into(),
-            links: value.read::<Count>("links").unwrap_or_default().into(),
-            ..default::Default::default()
+            date,
+            date_source,
+            parents: read_all(&config.fields.parents),
+            hashtags: read_all(&config.fields.tags),
+            aliases: read_all(&config.fields.aliases),
+            links,
+            items: Vec::new(),
+            metadata,
+            mtime: None,
+            size: 0,
+            name: path
+                .file_name()
+                .unwrap_or_default()
+                .to_string_lossy()
+                .into(),
+            path,
         }
     }
 }
@@ -142,10 +156,17 @@ impl Display for FileData {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
         write!(
             f,
-            "{} -[🔗{:>3}]- {}\n<:{}::{}>",
+            "{} {:<10} -[🔗{:>3}]- {}\n<:{}::{}>",
             self.date.to_string().yellow(),
-            self.links,
-            self.name,
+            format!(
+                "({})",
+                self.date_source
+                    .map(|s| s.to_string())
+                    .unwrap_or("none".into())
+            )
+            .dimmed(),
+            self.links.iter().filter(|l| l.is_internal()).count(),
+            self.path.display(),
             self.hashtags
                 .iter()
                 .map(|i| i.underline().to_string())
@@ -160,84 +181,221 @@ impl Display for FileData {
     }
 }
 
-fn extract_keywords(stream: Vec<&str>) -> HashMap<String, String> {
-    let hashtag_rexp: Regex = Regex::new("^#[a-zA-Z0-9/_]+").expect("Regex Error");
-    let link_rexp: Regex = Regex::new(r#"\[\[([^\]\]])*\]\]"#).expect("Regex Error");
-    let mut metadata = HashMap::<String, String>::new();
-    for s in stream {
-        if let Some((key, value)) = s.split_once("::") {
-            for val in value.split(",") {
-                match metadata.get(key) {
-                    Some(v) => metadata.insert(key.into(), format!("{} :: {}", v, val.trim())),
-                    None => metadata.insert(key.into(), val.trim().into()),
-                };
+/// Read the value of an inline field, splitting it into a list where needed.
+fn field_value(raw: &str, config: &Config) -> Value {
+    match Value::parse(raw) {
+        // Dates such as `March 1, 2024` must not be split on the comma
+        date @ (Value::Date(_) | Value::DateTime(_)) => date,
+        _ => {
+            let mut value = Value::Null;
+            for val in raw.split(config.value_split.as_str()) {
+                for item in val.split(config.separator.as_str()) {
+                    value.push(Value::parse(item));
+                }
             }
+            value
         }
-        for token in s.split_whitespace() {
-            if hashtag_rexp.captures(token).is_some() {
-                match metadata.get("hashtag") {
-                    Some(v) => {
-                        metadata.insert("hashtag".into(), format!("{} :: {}", v, token.trim()))
-                    }
-                    None => metadata.insert("hashtag".into(), token.trim().into()),
-                };
+    }
+}
+
+/// `first_line` is the line number of the first line of `body` in the note.
+///
+/// Inline fields on list items and tasks go to that [ListItem] instead of the
+/// note-level [Metadata].
+fn extract_keywords(body: &str, first_line: usize, config: &Config) -> (Metadata, Vec<ListItem>) {
+    let mut metadata = Metadata::new();
+    let mut items = Vec::new();
+    let mut hashtags = Vec::new();
+    let mut links = Vec::new();
+    let prose = markdown::mask_code(body);
+    for (n, (s, prose)) in body.lines().zip(prose.lines()).enumerate() {
+        let mut item = ListItem::parse(prose, s, first_line + n);
+        let line_links = links::extract(prose, s, first_line + n);
+        let line_tags = markdown::hashtags(prose);
+        for field in fields::inline_fields(prose, s) {
+            let value = field_value(field.value, config);
+            let fields = match item.as_mut() {
+                Some(i) => &mut i.fields,
+                None => &mut metadata,
+            };
+            // Like Dataview, `Due Date::` is readable as both `Due Date` and `due-date`
+            let canonical = field.canonical_key();
+            if canonical != field.key {
+                fields
+                    .entry(canonical)
+                    .or_insert(Value::Null)
+                    .push(value.clone());
             }
+            fields.entry(field.key).or_insert(Value::Null).push(value);
         }
-        for l in link_rexp.captures_iter(s) {
-            match metadata.get("links") {
-                Some(c) => metadata.insert("links".into(), format!("{}{}", c, "x")),
-                None => metadata.insert("links".into(), "x".into()),
-            };
+        if let Some(mut item) = item {
+            item.tags = line_tags.iter().map(|t| t.to_string()).collect();
+            item.links = line_links.clone();
+            items.push(item);
+        }
+        hashtags.extend(line_tags.into_iter().map(|t| Value::Text(t.into())));
+        links.extend(line_links.into_iter().map(Value::Link));
+    }
+    if !hashtags.is_empty() {
+        metadata.insert("hashtag".into(), Value::List(hashtags));
+    }
+    metadata.insert("links".into(), Value::List(links));
+    (metadata, items)
+}
+
+/// Every note of a vault, along with the other files in it.
+struct Vault {
+    notes: Vec<FileData>,
+    /// Files that are not notes, such as images and PDFs
+    attachments: Vec<PathBuf>,
+}
+
+impl Vault {
+    fn resolver(&self) -> Resolver {
+        Resolver::new(&self.notes, &self.attachments)
+    }
+
+    /// The note a name, path or alias given on the command line refers to,
+    /// looked up like a `[[link]]` from the vault root.
+    fn find(&self, name: &str) -> Result<&FileData, std::io::Error> {
+        let not_found = || {
+            std::io::Error::new(
+                std::io::ErrorKind::NotFound,
+                format!("no note called `{}`", name),
+            )
+        };
+        let path = match self.resolver().resolve(&Link::wiki(name), Path::new("")) {
+            Resolution::Resolved(p) => p,
+            Resolution::Ambiguous(paths) => {
+                return Err(std::io::Error::new(
+                    std::io::ErrorKind::InvalidInput,
+                    format!(
+                        "`{}` matches more than one note: {}",
+                        name,
+                        paths
+                            .iter()
+                            .map(|p| p.display().to_string())
+                            .collect::<Vec<String>>()
+                            .join(", ")
+                    ),
+                ))
+            }
+            Resolution::Unresolved => return Err(not_found()),
+        };
+        // An attachment can resolve too, but it is not a note
+        self.notes
+            .iter()
+            .find(|n| n.path == path)
+            .ok_or_else(not_found)
+    }
+}
+
+/// Read every note in the vault into [FileData], sorted by date.
+fn read_vault(vault: &VaultArgs) -> Result<Vault, std::io::Error> {
+    let config = Config::load(&vault.from, vault.config.as_deref())?;
+    let dates = DateResolver::new(&vault.from, &config.date_sources);
+    let mut file_vec: Vec<FileData> = Vec::new();
+    let mut attachments = Vec::new();
+    for path in vault::Walk::new(&vault.from)
+        .max_depth(vault.max_depth)
+        .files()?
+    {
+        if !vault::is_note(&path) {
+            attachments.push(path);
+            continue;
         }
+        let file = vault.from.join(&path);
+        let content = fs::read_to_string(&file)?;
+        let (metadata, items) = read_metadata(&content, &path, &config);
+        let mut note = FileData::new(path, metadata, &config, &dates);
+        note.items = items;
+        note.size = content.len() as u64;
+        note.mtime = fs::metadata(&file)
+            .and_then(|m| m.modified())
+            .ok()
+            .map(|t| chrono::DateTime::<chrono::Local>::from(t).naive_local());
+        file_vec.push(note);
     }
-    metadata
+    // The path breaks ties so that notes of the same day keep the same numbers everywhere
+    file_vec.sort_by(|a, b| (a.date, &a.path).cmp(&(b.date, &b.path)));
+    Ok(Vault {
+        notes: file_vec,
+        attachments,
+    })
+}
+
+/// Collect the metadata of a note from its YAML frontmatter and inline fields.
+///
+/// When both sources set the same key, the order is:
+/// 1. Frontmatter is read first and acts as the default.
+/// 2. An inline `key:: value` field replaces the frontmatter value of that key.
+/// 3. Hashtags in the text are kept under `hashtag`, apart from frontmatter `tags`,
+///    so both end up in [FileData] through the `tags` field mapping.
+fn read_metadata(content: &str, path: &Path, config: &Config) -> (Metadata, Vec<ListItem>) {
+    let (yaml, body) = frontmatter::split(content);
+    let mut metadata = match yaml.map(frontmatter::parse) {
+        Some(Ok(m)) => m,
+        Some(Err(e)) => {
+            eprintln!("{} {}: {}", "warning:".yellow(), path.display(), e);
+            Metadata::new()
+        }
+        None => Metadata::new(),
+    };
+    let first_line = content[..content.len() - body.len()].lines().count() + 1;
+    let (inline, items) = extract_keywords(body, first_line, config);
+    metadata.extend(inline);
+    (metadata, items)
 }
 
 impl Command for FileList {
     fn execute(&self) -> Result<(), std::io::Error> {
         let pattern = Regex::new(&self.with).expect("Regex build error");
-        let paths = fs::read_dir(&self.from)?;
-        let mut file_vec: Vec<FileData> = Vec::new();
-        for path in paths {
-            let file = path.unwrap().path();
-            if file.is_file() {
-                let name = file.file_name().unwrap();
-                let content = fs::read_to_string(file.clone())?;
-
-                let stream: Vec<&str> = content.split_terminator(&['\r', '\n'][..]).collect();
-                let metadata = extract_keywords(stream);
-                // let (key, value) = stream[0].split_once("::").unwrap_or(("", ""));
-                // if DATE_RE.captures(value).is_some() {
-                file_vec.push(FileData::from(metadata).with_name(name.to_str().unwrap().into()));
-                // println!("{:#?}", &file_vec);
-                // }
-            }
+        let vault = read_vault(&self.vault)?;
+        // Machine formats on stdout get nothing but the notes
+        if self.to.is_some() || self.format.is_none() {
+            println!("Read {} files", &vault.notes.len());
         }
-        file_vec.sort_by_key(|x| x.date);
-        println!("Read {} files", &file_vec.len());
-        let file_vec = file_vec
-            .iter()
+        let mut file_vec = self
+            .filters
+            .apply(&vault, &self.vault.from)?
+            .into_iter()
             .filter(|&i| pattern.captures(&i.name).is_some())
             .collect::<Vec<&FileData>>();
-        if self.to.is_some() {
-            let mut list = fs::File::create(&self.clone().to.unwrap())?;
-            for i in &file_vec {
-                list.write_all(format!("{}\n", i).as_bytes()).unwrap();
+        self.sort.apply(&mut file_vec);
+        if let Some(limit) = self.limit {
+            file_vec.truncate(limit);
+        }
+        if let Some(to) = &self.to {
+            colored::control::set_override(false);
+            let mut list = fs::File::create(to)?;
+            match self.format {
+                Some(format) => note_table(&file_vec).write(format, &mut list)?,
+                None => {
+                    for i in &file_vec {
+                        list.write_all(format!("{}\n", i).as_bytes())?;
+                    }
+                }
             }
+        } else if let Some(format) = self.format {
+            note_table(&file_vec).write(format, &mut std::io::stdout().lock())?;
         } else {
             for f in file_vec
                 .iter()
                 .enumerate()
                 .map(|(n, i)| format!("[{: >2}] {}", n + 1, i))
-                .take(self.limit)
+                .take(self.limit.unwrap_or(10))
             {
                 println!("{}", f);
             }
             println!("---");
+            // The same filters and order give `read` the same numbers
+            let mut args = vec![format!("--with \"{}\"", self.with)];
+            args.extend(self.filters.args());
+            args.extend(self.sort.args());
             println!(
-                "Use `cli read {} -n <N> --with \"{}\"` to read content number <N>",
-                self.from.as_path().as_os_str().to_str().unwrap(),
-                self.with
+                "Use `cli read {} -n <N> {}` to read content number <N>",
+                self.vault.from.as_path().as_os_str().to_str().unwrap(),
+                args.join(" ")
             )
         }
         Ok(())
@@ -247,57 +405,195 @@ impl Command for FileList {
 impl Command for FileRead {
     fn execute(&self) -> Result<(), std::io::Error> {
         let pattern = Regex::new(&self.with).expect("Regex build error");
-        let paths = fs::read_dir(&self.from)?;
-        let mut file_vec: Vec<FileData> = Vec::new();
-        for path in paths {
-            let file = path.unwrap().path();
-            if file.is_file() {
-                let name = file.file_name().unwrap();
-                let content = fs::read_to_string(file.clone())?;
-
-                let stream: Vec<&str> = content.split_terminator(&['\r', '\n'][..]).collect();
-                let metadata = extract_keywords(stream);
-                file_vec.push(FileData::from(metadata).with_name(name.to_str().unwrap().into()))
-            }
-        }
-        file_vec.sort_by_key(|x| x.date);
-        let file_vec = file_vec
-            .iter()
+        let vault = read_vault(&self.vault)?;
+        let mut file_vec = self
+            .filters
+            .apply(&vault, &self.vault.from)?
+            .into_iter()
             .filter(|&i| pattern.captures(&i.name).is_some())
             .collect::<Vec<&FileData>>();
-        let re = fs::read_to_string(PathBuf::from_iter(vec![
-            self.from.clone(),
-            file_vec[self.number.saturating_sub(1)].clone().name.into(),
-        ]))?;
-        println!("{}", file_vec[self.number.saturating_sub(1)]);
-        println!("{}", re);
+        self.sort.apply(&mut file_vec);
+        let note = *file_vec.get(self.number.saturating_sub(1)).ok_or_else(|| {
+            std::io::Error::new(
+                std::io::ErrorKind::NotFound,
+                format!("only {} notes match", file_vec.len()),
+            )
+        })?;
+        let re = fs::read_to_string(self.vault.from.join(&note.path))?;
+        println!("{}", note);
+        if self.links {
+            let resolver = vault.resolver();
+            for link in note.links.iter().filter(|l| l.is_internal()) {
+                let to = match resolver.resolve(link, &note.path) {
+                    Resolution::Resolved(p) => p.display().to_string().green(),
+                    Resolution::Ambiguous(p) => format!(
+                        "ambiguous: {}",
+                        p.iter()
+                            .map(|p| p.display().to_string())
+                            .collect::<Vec<String>>()
+                            .join(", ")
+                    )
+                    .yellow(),
+                    Resolution::Unresolved => "unresolved".red(),
+                };
+                println!("{:>4}: {} -> {}", link.line, link, to);
+            }
+            println!("---");
+        }
+        if self.items {
+            for item in &note.items {
+                let mut fields: Vec<String> = item
+                    .fields
+                    .iter()
+                    .map(|(k, v)| format!("{}={}", k, v))
+                    .collect();
+                fields.sort();
+                println!(
+                    "{:>4}: {}{} {} {}",
+                    item.line,
+                    "  ".repeat(item.indent / 2),
+                    match item.status {
+                        Some(s) => format!("[{}]", s),
+                        None => "-".into(),
+                    },
+                    item.text,
+                    fields.join(" ").blue()
+                );
+            }
+            println!("---");
+        }
+        if self.raw && !self.ast {
+            println!("{}", re);
+            return Ok(());
+        }
+        let (yaml, body) = frontmatter::split(&re);
+        let mut doc = document::DocumentAST::parse(body, note.metadata.clone());
+        if self.expand_embeds {
+            doc.content = embed::Transclusion::new(&vault, &self.vault.from, self.embed_depth)
+                .expand(doc.content, &note.path);
+        }
+        if self.ast {
+            print!("{}", doc);
+        } else {
+            if let Some(yaml) = yaml {
+                println!("{}", format!("---\n{}\n---", yaml.trim_end()).dimmed());
+            }
+            // Rules span the terminal when the shell exports its width
+            let width = std::env::var("COLUMNS")
+                .ok()
+                .and_then(|c| c.parse().ok())
+                .unwrap_or(80);
+            println!("{}", terminal::Terminal::new(width).render(&doc));
+        }
         Ok(())
     }
 }
 
+/// The notes as rows with their file details and every metadata field.
+///
+/// File details use the `file.*` names of `query`, so they cannot clash with
+/// metadata keys such as `tags`.
+fn note_table(notes: &[&FileData]) -> output::Table {
+    let mut headers: Vec<String> = [
+        "file.path",
+        "file.name",
+        "file.date",
+        "file.tags",
+        "file.parents",
+        "file.aliases",
+        "file.outlinks",
+        "file.mtime",
+        "file.size",
+    ]
+    .map(String::from)
+    .to_vec();
+    let mut keys: Vec<&String> = notes.iter().flat_map(|n| n.metadata.keys()).collect();
+    keys.sort();
+    keys.dedup();
+    headers.extend(keys.into_iter().cloned());
+    output::Table {
+        rows: notes
+            .iter()
+            .map(|n| headers.iter().map(|h| dql::field(n, h)).collect())
+            .collect(),
+        headers,
+    }
+}
+
+/// Location of the vault and how deep to look into it
 #[derive(Args, Clone)]
-struct FileList {
+struct VaultArgs {
     from: PathBuf,
+    /// How many folders deep to search for notes (0 = vault root only)
+    #[arg(long)]
+    max_depth: Option<usize>,
+    /// Config file to use instead of `karat.toml` in the vault root
+    #[arg(long)]
+    config: Option<PathBuf>,
+}
+
+#[derive(Args, Clone)]
+struct FileList {
+    #[command(flatten)]
+    vault: VaultArgs,
     to: Option<PathBuf>,
-    #[arg(long, default_value_t = 10)]
-    limit: usize,
+    /// How many notes to show, 10 on a terminal and all of them otherwise
+    #[arg(long)]
+    limit: Option<usize>,
     #[arg(long, default_value = ".")]
     with: String,
+    #[command(flatten)]
+    filters: filter::Filters,
+    #[command(flatten)]
+    sort: sort::SortArgs,
+    /// Print the notes in a machine-readable format instead, with all their fields
+    #[arg(long, value_enum)]
+    format: Option<output::Format>,
 }
 
 #[derive(Args)]
 struct FileRead {
-    from: PathBuf,
+    #[command(flatten)]
+    vault: VaultArgs,
     #[arg(short)]
     number: usize,
     #[arg(long, default_value = ".")]
     with: String,
+    #[command(flatten)]
+    filters: filter::Filters,
+    #[command(flatten)]
+    sort: sort::SortArgs,
+    /// List the links of the note and where they resolve to
+    #[arg(long)]
+    links: bool,
+    /// List the list items and tasks of the note with their fields
+    #[arg(long)]
+    items: bool,
+    /// Show the parsed document tree instead of the text
+    #[arg(long)]
+    ast: bool,
+    /// Print the text as it is in the file instead of rendering it
+    #[arg(long)]
+    raw: bool,
+    /// Show embedded notes, sections and blocks in place of their `![[embeds]]`
+    #[arg(long)]
+    expand_embeds: bool,
+    /// How many embeds deep to expand
+    #[arg(long, default_value_t = embed::MAX_DEPTH)]
+    embed_depth: usize,
 }
 
 #[derive(Parser)]
 enum App {
     List(FileList),
     Read(FileRead),
+    Backlinks(backlinks::Backlinks),
+    Check(check::Check),
+    Tree(tree::Tree),
+    Tags(tags::Tags),
+    Tasks(tasks::Tasks),
+    Query(query::Query),
+    Convert(convert::Convert),
 }
 
 trait Command {
@@ -305,565 +601,27 @@ trait Command {
 }
 
 fn main() {
+    // `colored` already follows `NO_COLOR`, but not whether stdout is a terminal
+    if !std::io::IsTerminal::is_terminal(&std::io::stdout()) {
+        colored::control::set_override(false);
+    }
     if let Err(e) = match App::parse() {
         App::List(cmd) => cmd.execute(),
         App::Read(cmd) => cmd.execute(),
+        App::Backlinks(cmd) => cmd.execute(),
+        App::Check(cmd) => cmd.execute(),
+        App::Tree(cmd) => cmd.execute(),
+        App::Tags(cmd) => cmd.execute(),
+        App::Tasks(cmd) => cmd.execute(),
+        App::Query(cmd) => cmd.execute(),
+        App::Convert(cmd) => cmd.execute(),
     } {
-        println!("{:?}", e)
+        // Output piped into `head` and the like is cut short on purpose
+        if e.kind() == std::io::ErrorKind::BrokenPipe {
+            return;
+        }
+        eprintln!("{} {}", "error:".red(), e);
+        // Kept apart from the 1 that `check` exits with when it finds problems
+        std::process::exit(2);
     }
 }
-
-// #[derive(Debug)]
-// struct SelfError;
-
-// fn parse_content(content: String) -> Result<DocumentAST, SelfError> {
-//     let parser = pulldown_cmark::Parser::new(content.as_str());
-//     Ok(DocumentAST::from(parser))
-// }
-// #[derive(Debug)]
-// struct DocumentMetadata;
-
-// impl Display for DocumentMetadata {
-//     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
-//         write!(f, "Metadata")
-//     }
-// }
-
-// #[derive(Debug)]
-// struct DocumentAST {
-//     meta: DocumentMetadata,
-//     content: Vec<BlockElement>,
-// }
-
-// impl Display for DocumentAST {
-//     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
-//         write!(
-//             f,
-//             "DocumentAST [\n\t({}){}\n] DocumenAST",
-//             self.meta,
-//             self.content
-//                 .iter()
-//                 .fold("".to_string(), |a, b| format!("{a}\n\t {}", b))
-//         )
-//     }
-// }
-
-// impl From<Parser<'_, '_>> for DocumentAST {
-//     fn from(value: Parser<'_, '_>) -> Self {
-//         let mut children: Vec<BlockElement> = Vec::new();
-//         let mut mut_val = value.map(|x| x).collect::<Vec<Event>>().into_iter();
-//         while let Some(event) = mut_val.next() {
-//             match event {
-//                 Event::End(_) => continue,
-//                 Event::Start(tag) => match tag {
-//                     Tag::Paragraph => {
-//                         let mut grandchild: Vec<Vec<InlineElement>> = Vec::new();
-//                         let mut grandgrandchild: Vec<InlineElement> = Vec::new();
-//                         while let Some(next) = mut_val.next() {
-//                             match next {
-//                                 Event::End(Tag::Paragraph) => break,
-//                                 Event::Text(s) | Event::Code(s) | Event::Html(s) => {
-//                                     grandgrandchild.push(InlineElement::Str(s.to_string()))
-//                                 }
-//                                 Event::Rule => {
-//                                     grandgrandchild.push(InlineElement::Str("---".to_string()))
-//                                 }
-//                                 Event::SoftBreak => grandgrandchild.push(InlineElement::SoftBreak),
-//                                 any => unimplemented!("Inside Tag::Paragraph -> {:?}", any),
-//                             }
-//                         }
-//                         grandchild.push(grandgrandchild);
-//                         children.push(BlockElement::LineBlock(grandchild).concat_string())
-//                     }
-//                     Tag::Heading(level, iden, classes) => {
-//                         let l: u8 = level as u8;
-//                         let ident = iden.unwrap_or_default().to_string();
-//                         if let Some(next) = mut_val.next() {
-//                             match next {
-//                                 Event::End(Tag::Heading(_, _, _)) => continue,
-//                                 Event::Text(s) | Event::Code(s) | Event::Html(s) => {
-//                                     children.push(BlockElement::Header(
-//                                         l,
-//                                         HTMLItem {
-//                                             ident,
-//                                             classes: classes
-//                                                 .iter()
-//                                                 .map(|x| x.to_owned().to_string())
-//                                                 .collect(),
-//                                             attrs: Vec::new(),
-//                                             children: InlineVec { items: vec![InlineElement::Str(s.to_string())] },
-//                                         },
-//                                     ))
-//                                 }
-//                                 any => unimplemented!("Inside Tag::Heading -> {:?}", any),
-//                             }
-//                         }
-//                     }
-//                     Tag::BlockQuote => {
-//                         let mut grandchild: Vec<BlockElement> = Vec::new();
-//                         while let Some(next) = mut_val.next() {
-//                             match next {
-//                                 Event::End(Tag::BlockQuote) => break,
-//                                 Event::Text(s) | Event::Code(s) | Event::Html(s) => grandchild
-//                                     .push(BlockElement::Plain(vec![InlineElement::Str(
-//                                         s.to_string(),
-//                                     )])),
-//                                 _ => continue,
-//                             }
-//                         }
-//                         children.push(BlockElement::BlockQuote(grandchild));
-//                     }
-//                     Tag::Emphasis => {
-//                         let mut grandchild: Vec<InlineElement> = Vec::new();
-//                         while let Some(next) = mut_val.next() {
-//                             match next {
-//                                 Event::End(Tag::BlockQuote) => break,
-//                                 Event::Text(s) | Event::Code(s) | Event::Html(s) => {
-//                                     grandchild.push(InlineElement::Str(s.to_string()))
-//                                 }
-//                                 _ => continue,
-//                             }
-//                         }
-//                         children.push(BlockElement::Plain(vec![InlineElement::Emph(grandchild)]));
-//                     }
-//                     Tag::Strong => {
-//                         let mut grandchild: Vec<InlineElement> = Vec::new();
-//                         while let Some(next) = mut_val.next() {
-//                             match next {
-//                                 Event::End(Tag::BlockQuote) => break,
-//                                 Event::Text(s) | Event::Code(s) | Event::Html(s) => {
-//                                     grandchild.push(InlineElement::Str(s.to_string()))
-//                                 }
-//                                 _ => continue,
-//                             }
-//                         }
-//                         children.push(BlockElement::Plain(vec![InlineElement::Strong(grandchild)]));
-//                     }
-//                     Tag::Strikethrough => {
-//                         let mut grandchild: Vec<InlineElement> = Vec::new();
-//                         while let Some(next) = mut_val.next() {
-//                             match next {
-//                                 Event::End(Tag::BlockQuote) => break,
-//                                 Event::Text(s) | Event::Code(s) | Event::Html(s) => {
-//                                     grandchild.push(InlineElement::Str(s.to_string()))
-//                                 }
-//                                 _ => continue,
-//                             }
-//                         }
-//                         children.push(BlockElement::Plain(vec![InlineElement::Strikeout(
-//                             grandchild,
-//                         )]));
-//                     }
-//                     Tag::List(num) => {
-//                         let mut grandgrandchild: Vec<Vec<BlockElement>> = Vec::new();
-//                         let mut grandgrandgrandchild: Vec<InlineElement> = Vec::new();
-//                         // TODO: Unnumberred list
-//                         while let Some(next) = mut_val.next() {
-//                             match next {
-//                                 Event::End(Tag::List(_)) => break,
-//                                 Event::Start(Tag::Item) => {
-//                                     while let Some(next) = mut_val.next() {
-//                                         match next {
-//                                                 Event::End(Tag::Item) => break,
-//                                                 Event::Text(s) => grandgrandgrandchild.push(InlineElement::Str(s.to_string())),
-//                                                 Event::Code(s) => grandgrandgrandchild.push(InlineElement::Code(HTMLItem{children: s.to_string(), ..Default::default()})),
-//                                                 Event::SoftBreak => grandgrandgrandchild.push(InlineElement::SoftBreak),
-//                                                 Event::Start(Tag::List(_)) => {
-//                                                     while let Some(next_) = mut_val.next() {
-//                                                         match next_ {
-//                                                             Event::End(Tag::List(_)) => break,
-//                                                             _ => continue
-//                                                         }
-//                                                     }
-//                                                     // todo!("Implement nested list Event::Start(Tag::List(Some(u64))) > Event::Start(Tag::Item) > Event::Start(Tag::List(Option<u64>))")
-//                                                 }
-//                                                 any => todo!("Event::Start(Tag::List(Some(u64))) > Event::Start(Tag::Item) > {:?}", any)
-//                                             }
-//                                     }
-//                                 }
-//                                 any => {
-//                                     unimplemented!("Event::Start(Tag::List(Some(u68)) > {:?}", any)
-//                                 }
-//                             }
-//                         }
-//                         grandgrandchild.push(vec![BlockElement::Plain(grandgrandgrandchild)]);
-//                         children.push(match num {
-//                             Some(i) => BlockElement::OrderedList(
-//                                 (
-//                                     num.unwrap_or(0) as u8,
-//                                     "decimal".to_string(),
-//                                     ".".to_string(),
-//                                 ),
-//                                 grandgrandchild,
-//                             ),
-//                             None => BlockElement::BulletList(grandgrandchild),
-//                         })
-//                     }
-//                     Tag::CodeBlock(kind) => {
-//                         // TODO
-//                         match kind {
-//                             CodeBlockKind::Indented => {}
-//                             CodeBlockKind::Fenced(_) => {}
-//                         }
-//                         while let Some(next) = mut_val.next() {
-//                             match next {
-//                                 Event::End(Tag::CodeBlock(_)) => break,
-//                                 _ => continue,
-//                             }
-//                         }
-//                     }
-//                     any => todo!("Event::Start({:?})", any),
-//                 },
-//                 Event::Rule => children.push(BlockElement::HorizontalRule),
-//                 Event::Text(s) => match s {
-//                     CowStr::Boxed(_) => todo!("Cowstr::Boxed(_)"),
-//                     CowStr::Borrowed(x) => {
-//                         children.push(BlockElement::Plain(vec![InlineElement::Str(x.to_string())]))
-//                     }
-//                     CowStr::Inlined(_) => todo!("Cowstr::Inlined(_)"),
-//                 },
-//                 Event::Code(s) => {
-//                     children.push(BlockElement::Plain(vec![InlineElement::Code(HTMLItem {
-//                         children: s.to_string(),
-//                         ..Default::default()
-//                     })]))
-//                 }
-//                 Event::SoftBreak => {
-//                     children.push(BlockElement::Plain(vec![InlineElement::SoftBreak]))
-//                 }
-//                 a => todo!("{:?}", a),
-//             }
-//         }
-//         Self {
-//             content: children,
-//             meta: DocumentMetadata,
-//         }
-//     }
-// }
-
-// #[derive(Debug)]
-// enum MathItem {
-//     Display(String),
-//     Inline(String),
-// }
-
-// impl Display for MathItem {
-//     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
-//         write!(
-//             f,
-//             "Math {}",
-//             match self {
-//                 MathItem::Display(s) => "Display {s}".to_string(),
-//                 MathItem::Inline(s) => "Inline {s}".to_string(),
-//             }
-//         )
-//     }
-// }
-
-// #[derive(Debug)]
-// struct LinkItem {
-//     ident: String,
-//     classes: Vec<String>,
-//     attrs: Vec<(String, String)>,
-//     children: Vec<InlineElement>,
-//     /// (URL, Text)
-//     target: (String, String),
-// }
-
-// impl Display for LinkItem {
-//     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
-//         write!(
-//             f,
-//             "Link\n({}, [{}], [{}])",
-//             self.ident,
-//             self.classes
-//                 .iter()
-//                 .fold("".to_string(), |a, b| format!("{a}, \"{b}\"")),
-//             self.attrs
-//                 .iter()
-//                 .fold("".to_string(), |a, (k, v)| format!("{a}, (\"{k}\", \"{v}\")"))
-//         )
-//     }
-// }
-
-// /// The struct resembling HTML tags, i.e. `<div>children</div>`
-// ///
-// /// Pandoc Native representation:
-// /// ```haskell
-// /// Elem
-// ///     (iden, classes, attrs)
-// ///     [ children ]
-// /// ```
-// #[derive(Debug, Default)]
-// struct HTMLItem<Child> {
-//     ident: String,
-//     classes: Vec<String>,
-//     attrs: Vec<(String, String)>,
-//     children: Child,
-// }
-
-// #[derive(Debug)]
-// struct BlockVec {
-//     items: Vec<BlockElement>,
-// }
-
-// impl Display for BlockVec {
-//     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
-//         write!(
-//             f,
-//             "{}",
-//             self.items
-//                 .iter()
-//                 .fold("".to_string(), |a, b| format!("{a} {b}"))
-//         )
-//     }
-// }
-// #[derive(Debug)]
-// struct InlineVec {
-//     items: Vec<InlineElement>,
-// }
-
-// impl Display for InlineVec {
-//     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
-//         write!(
-//             f,
-//             "{}",
-//             self.items
-//                 .iter()
-//                 .fold("".to_string(), |a, b| format!("{a} {b}"))
-//         )
-//     }
-// }
-// #[derive(Debug)]
-// struct StringVec {
-//     items: Vec<String>,
-// }
-
-// impl Display for StringVec {
-//     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
-//         write!(
-//             f,
-//             "[{}]",
-//             self.items
-//                 .iter()
-//                 .fold("".to_string(), |a, b| format!("{a} \"{b}\""))
-//         )
-//     }
-// }
-
-// impl<Child> Display for HTMLItem<Child>
-// where
-//     Child: Display,
-// {
-//     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
-//         write!(
-//             f,
-//             "\n\t\t{}",
-//             format!(
-//                 "({}, {}, {})\n\t\t{}",
-//                 self.ident,
-//                 self.classes
-//                     .iter()
-//                     .fold("".to_string(), |a, b| format!("{a}, \"{b}\"")),
-//                 self.attrs
-//                     .iter()
-//                     .fold("".to_string(), |a, (k, v)| format!("{a}, (\"{k}\", \"{v}\")")),
-//                 self.children
-//             )
-//         )
-//     }
-// }
-
-// // TODO
-// #[derive(Debug)]
-// struct TableTag;
-
-// #[derive(Debug)]
-// enum QuoteType {
-//     SingleQuote,
-//     DoubleQuote,
-// }
-
-// // TODO
-// #[derive(Debug)]
-// struct Citation;
-
-// /// Elements are defined from [pandoc](https://hackage.haskell.org/package/pandoc-types-1.22/docs/Text-Pandoc-Definition.html)
-// #[derive(Debug)]
-// enum Element {
-//     Inline(InlineElement),
-//     Block(BlockElement),
-// }
-
-// impl From<Event<'_>> for Element {
-//     fn from(value: Event<'_>) -> Self {
-//         match value {
-//             Event::Rule => Self::Inline(InlineElement::SoftBreak),
-//             Event::Text(s) | Event::Code(s) | Event::Html(s) => {
-//                 Self::Inline(InlineElement::Str(s.to_string()))
-//             }
-//             _ => Self::Block(BlockElement::Null),
-//         }
-//     }
-// }
-
-// #[derive(Debug)]
-// enum InlineElement {
-//     Str(String),
-//     Emph(Vec<InlineElement>),
-//     Underline(Vec<InlineElement>),
-//     Strong(Vec<InlineElement>),
-//     Strikeout(Vec<InlineElement>),
-//     Superscript(Vec<InlineElement>),
-//     Subscript(Vec<InlineElement>),
-//     SmallCaps(Vec<InlineElement>),
-//     Quoted(QuoteType, Vec<Element>),
-//     Cite(Vec<Citation>, Vec<InlineElement>),
-//     Code(HTMLItem<String>),
-//     Space,
-//     SoftBreak,
-//     LineBreak,
-//     Math(MathItem),
-//     /// (Format, Text)
-//     RawInline(String, String),
-//     Link(LinkItem),
-//     Image(LinkItem),
-//     Note(BlockElement),
-//     Span(HTMLItem<InlineVec>),
-// }
-
-// impl Display for InlineElement {
-//     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
-//         write!(
-//             f,
-//             "{}",
-//             match self {
-//                 InlineElement::Str(s) => format!("Str {s}"),
-//                 InlineElement::Emph(s) => format!("Emph [{:?}]", s),
-//                 InlineElement::Underline(s) => format!("Underline [{:?}]", s),
-//                 InlineElement::Strong(s) => format!("Strong [{:?}]", s),
-//                 InlineElement::Strikeout(s) => format!("Strikeout [{:?}]", s),
-//                 InlineElement::Superscript(s) => format!("Superscript [{:?}]", s),
-//                 InlineElement::Subscript(s) => format!("Subscript [{:?}]", s),
-//                 InlineElement::SmallCaps(s) => format!("SmallCaps [{:?}]", s),
-//                 InlineElement::Quoted(t, s) => format!("Quoted ({:?} [{:?}])", t, s),
-//                 InlineElement::Cite(c, s) => format!("Cite ([{:?}] [{:?}])", c, s),
-//                 InlineElement::Code(s) => format!("Code {s}"),
-//                 InlineElement::Space => format!("Space"),
-//                 InlineElement::SoftBreak => format!("SoftBreak"),
-//                 InlineElement::LineBreak => format!("LineBreak"),
-//                 InlineElement::Math(s) => format!("Math {s}"),
-//                 InlineElement::RawInline(f, s) => format!("RawInLine {f} {s}"),
-//                 InlineElement::Link(s) => format!("Link {s}"),
-//                 InlineElement::Image(s) => format!("Image {s}"),
-//                 InlineElement::Note(s) => format!("Note {s}"),
-//                 InlineElement::Span(s) => format!("Span {:#?}", s),
-//             }
-//         )
-//     }
-// }
-
-// impl InlineElement {
-//     pub fn to_string(&self) -> String {
-//         match self {
-//             InlineElement::Str(s) => s.clone(),
-//             InlineElement::SoftBreak => "\n".to_string(),
-//             any => todo!("InlineElement.to_string() for {:?}", any),
-//         }
-//     }
-// }
-// #[derive(Debug)]
-// enum BlockElement {
-//     Plain(Vec<InlineElement>),
-//     Para(Vec<InlineElement>),
-//     LineBlock(Vec<Vec<InlineElement>>),
-//     CodeBlock(HTMLItem<String>),
-//     /// (Format, Text)
-//     RawBlock(String, String),
-//     BlockQuote(Vec<BlockElement>),
-//     /// ((numbering, style, delim), \[Block\])
-//     OrderedList((u8, String, String), Vec<Vec<BlockElement>>),
-//     BulletList(Vec<Vec<BlockElement>>),
-//     DefinitionList(Vec<(Vec<InlineElement>, Vec<Vec<BlockElement>>)>),
-//     Header(u8, HTMLItem<InlineVec>),
-//     HorizontalRule,
-//     Table(TableTag),
-//     Div(HTMLItem<BlockVec>),
-//     Null,
-// }
-
-// impl Display for BlockElement {
-//     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
-//         write!(
-//             f,
-//             "{}",
-//             match self {
-//                 BlockElement::Plain(s) => s
-//                     .iter()
-//                     .fold("Plain ".to_string(), |a, b| format!("{a} {b}")),
-//                 BlockElement::Para(s) => s
-//                     .iter()
-//                     .fold("Para ".to_string(), |a, b| format!("{a} {b}")),
-//                 BlockElement::LineBlock(s) =>
-//                     s.iter().fold("LineBlock".to_string(), |a, b| format!(
-//                         "{a} {}",
-//                         b.iter().fold("".to_string(), |x, y| format!("{x} {y}")),
-//                     )),
-//                 BlockElement::CodeBlock(s) => "CodeBlock {s}".to_string(),
-//                 BlockElement::RawBlock(f, s) => "RawBlock {f} {s}".to_string(),
-//                 BlockElement::BlockQuote(s) => s
-//                     .iter()
-//                     .fold("BlockQuote".to_string(), |a, b| format!("{a} {b}")),
-//                 BlockElement::OrderedList((i, f, d), s) => format!(
-//                     "OrderedList ({i}, {f}, {d}) {}",
-//                     s.iter().fold("".to_string(), |a, b| format!(
-//                         "{a} {}",
-//                         b.iter().fold("".to_string(), |x, y| format!("{x} {y}"))
-//                     ))
-//                 ),
-//                 BlockElement::BulletList(s) =>
-//                     s.iter().fold("LineBlock".to_string(), |a, b| format!(
-//                         "{a} {}",
-//                         b.iter().fold("".to_string(), |x, y| format!("{x} {y}")),
-//                     )),
-//                 BlockElement::DefinitionList(s) => format!("DefinitionList {:?}", s),
-//                 BlockElement::Header(i, s) => format!("Header {i} {s}"),
-//                 BlockElement::HorizontalRule => "HorizontalRule".to_string(),
-//                 BlockElement::Table(s) => format!("{:#?}", s),
-//                 BlockElement::Div(s) => format!("Div {s}"),
-//                 BlockElement::Null => "Null".to_string(),
-//             }
-//         )
-//     }
-// }
-
-// impl BlockElement {
-//     pub fn concat_string(self) -> Self {
-//         match self {
-//             // TODO Handle case where other element is between two str element
-//             BlockElement::Plain(s) => {
-//                 let v = s.iter().fold("".to_string(), |a, b| {
-//                     format!("{a}{}", b.to_string().as_str())
-//                 });
-//                 BlockElement::Plain(vec![InlineElement::Str(v)])
-//             }
-//             BlockElement::Para(s) => {
-//                 let v = s.iter().fold("".to_string(), |a, b| {
-//                     format!("{a}{}", b.to_string().as_str())
-//                 });
-//                 BlockElement::Para(vec![InlineElement::Str(v)])
-//             }
-//             BlockElement::LineBlock(ss) => {
-//                 let mut new: Vec<InlineElement> = Vec::new();
-//                 for s in ss {
-//                     let v = s.iter().fold("".to_string(), |a, b| {
-//                         format!("{a}{}", b.to_string().as_str())
-//                     });
-//                     new.push(InlineElement::Str(v));
-//                 }
-//                 BlockElement::LineBlock(vec![new])
-//             }
-//             any => todo!("BlockElement::concat_string() for {:?}", any),
-//         }
-//     }
-// }
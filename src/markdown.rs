@@ -0,0 +1,85 @@
+use lazy_static::lazy_static;
+use pulldown_cmark::{Event, Options, Parser, Tag};
+use regex::Regex;
+
+/// Blank out code blocks and inline code, keeping every byte offset and line break.
+///
+/// Extractors that work line by line can then run on the result without
+/// picking up `#tags` or fields from inside code.
+pub fn mask_code(body: &str) -> String {
+    let mut masked = body.as_bytes().to_vec();
+    let mut in_block = None;
+    for (event, range) in Parser::new_ext(body, Options::all()).into_offset_iter() {
+        match event {
+            Event::Start(Tag::CodeBlock(_)) => in_block = Some(range),
+            Event::End(Tag::CodeBlock(_)) => {
+                if let Some(block) = in_block.take() {
+                    blank(&mut masked[block]);
+                }
+            }
+            Event::Code(_) => blank(&mut masked[range]),
+            _ => {}
+        }
+    }
+    // The ranges cover whole characters, so the bytes left are still valid UTF-8
+    String::from_utf8(masked).unwrap_or_else(|_| body.into())
+}
+
+fn blank(bytes: &mut [u8]) {
+    for b in bytes.iter_mut().filter(|b| **b != b'\n' && **b != b'\r') {
+        *b = b' ';
+    }
+}
+
+lazy_static! {
+    /// A `#` at the start of a line or after whitespace, followed by Unicode
+    /// letters, marks, digits, `_`, `-` or `/`, which is what Obsidian accepts.
+    static ref HASHTAG_RE: Regex =
+        Regex::new(r"(?:^|\s)(?P<tag>#[\p{L}\p{M}\p{N}_/-]+)").expect("Regex run error");
+}
+
+/// Find the `#tags` on one line of prose, following Obsidian's tag grammar.
+///
+/// A tag needs a letter or digit and cannot be made only of digits, so `#1984`
+/// and `#-` are not tags but `#y1984` is.
+/// `#` inside a word or URL, as in `page#section`, does not start a tag, and
+/// neither does `##`.
+pub fn hashtags(line: &str) -> Vec<&str> {
+    HASHTAG_RE
+        .captures_iter(line)
+        .filter_map(|c| c.name("tag"))
+        .map(|m| m.as_str().trim_end_matches('/'))
+        .filter(|tag| tag.chars().any(char::is_alphanumeric))
+        .filter(|tag| tag.chars().any(|c| !c.is_numeric() && c != '/' && c != '#'))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn hashtags_follow_obsidian_grammar() {
+        assert_eq!(
+            hashtags("#kerja #café #جاوي #a-b/c_d #y1984"),
+            ["#kerja", "#café", "#جاوي", "#a-b/c_d", "#y1984"]
+        );
+        assert_eq!(hashtags("#1984 #2024/01 ## heading"), Vec::<&str>::new());
+        assert_eq!(hashtags("#- #_/ #1-2 #_a"), ["#1-2", "#_a"]);
+        assert_eq!(
+            hashtags("page#section https://x.org/#top"),
+            Vec::<&str>::new()
+        );
+        assert_eq!(hashtags("#nested/"), ["#nested"]);
+    }
+
+    #[test]
+    fn code_is_masked() {
+        let body = "#real `#inline`\n```\n#fenced\n```\n";
+        let masked = mask_code(body);
+        assert_eq!(masked.len(), body.len());
+        assert_eq!(masked.lines().count(), body.lines().count());
+        let tags: Vec<&str> = masked.lines().flat_map(hashtags).collect();
+        assert_eq!(tags, ["#real"]);
+    }
+}
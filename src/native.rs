@@ -0,0 +1,399 @@
+use crate::document::{
+    self, BlockElement as Block, HTMLItem, InlineElement as Inline, MathItem, TableTag,
+};
+use crate::pandoc::{alignment_name, delim_name, MetaValue, Pandoc};
+
+/// Lines are broken once they grow past this, as with pandoc's default `--columns`
+const WIDTH: usize = 72;
+
+/// A Haskell value as `show` writes it.
+enum Haskell {
+    /// A constructor applied to its arguments, e.g. `Str "word"`
+    Con(&'static str, Vec<Haskell>),
+    List(Vec<Haskell>),
+    Tuple(Vec<Haskell>),
+    /// A record with one field, e.g. `Meta { unMeta = ... }`
+    Record(&'static str, &'static str, Box<Haskell>),
+    Str(String),
+    Int(u64),
+}
+
+impl Haskell {
+    /// The value on one line, with spaces inside brackets as pandoc writes them.
+    fn flat(&self, argument: bool) -> String {
+        let join = |items: &[Haskell]| -> String {
+            items
+                .iter()
+                .map(|i| i.flat(false))
+                .collect::<Vec<String>>()
+                .join(" , ")
+        };
+        match self {
+            Haskell::Con(name, args) if args.is_empty() => name.to_string(),
+            Haskell::Con(name, args) => {
+                let args: Vec<String> = args.iter().map(|a| a.flat(true)).collect();
+                let con = format!("{} {}", name, args.join(" "));
+                match argument {
+                    true => format!("({})", con),
+                    false => con,
+                }
+            }
+            Haskell::List(items) if items.is_empty() => "[]".into(),
+            Haskell::List(items) => format!("[ {} ]", join(items)),
+            Haskell::Tuple(items) => format!("( {} )", join(items)),
+            Haskell::Record(name, field, value) => {
+                format!("{} {{ {} = {} }}", name, field, value.flat(false))
+            }
+            Haskell::Str(s) => show(s),
+            Haskell::Int(n) => n.to_string(),
+        }
+    }
+
+    /// Write the value starting at column `col`, on one line when it fits
+    /// and otherwise with each argument or item on a line of its own.
+    fn render(&self, col: usize, argument: bool, out: &mut String) {
+        let flat = self.flat(argument);
+        if col + flat.chars().count() <= WIDTH {
+            out.push_str(&flat);
+            return;
+        }
+        let newline = |out: &mut String, col: usize| {
+            out.push('\n');
+            out.push_str(&" ".repeat(col));
+        };
+        match self {
+            Haskell::Con(name, args) if !args.is_empty() => {
+                // Arguments line up two columns in from the constructor
+                let col = match argument {
+                    true => {
+                        out.push('(');
+                        col + 1
+                    }
+                    false => col,
+                };
+                out.push_str(name);
+                for arg in args {
+                    newline(out, col + 2);
+                    arg.render(col + 2, true, out);
+                }
+                if argument {
+                    out.push(')');
+                }
+            }
+            Haskell::List(items) | Haskell::Tuple(items) if !items.is_empty() => {
+                let (open, close) = match self {
+                    Haskell::List(_) => ("[ ", ']'),
+                    _ => ("( ", ')'),
+                };
+                for (n, item) in items.iter().enumerate() {
+                    match n {
+                        0 => out.push_str(open),
+                        _ => {
+                            newline(out, col);
+                            out.push_str(", ");
+                        }
+                    }
+                    item.render(col + 2, false, out);
+                }
+                newline(out, col);
+                out.push(close);
+            }
+            Haskell::Record(name, field, value) => {
+                out.push_str(name);
+                newline(out, col + 2);
+                out.push_str(&format!("{{ {} =", field));
+                newline(out, col + 6);
+                value.render(col + 6, false, out);
+                newline(out, col + 2);
+                out.push('}');
+            }
+            _ => out.push_str(&flat),
+        }
+    }
+}
+
+/// Quote text the way Haskell's `show` does: control characters by name,
+/// anything outside ASCII as a decimal escape.
+fn show(text: &str) -> String {
+    const NAMES: [&str; 32] = [
+        "NUL", "SOH", "STX", "ETX", "EOT", "ENQ", "ACK", "a", "b", "t", "n", "v", "f", "r", "SO",
+        "SI", "DLE", "DC1", "DC2", "DC3", "DC4", "NAK", "SYN", "ETB", "CAN", "EM", "SUB", "ESC",
+        "FS", "GS", "RS", "US",
+    ];
+    let mut out = String::from('"');
+    let mut chars = text.chars().peekable();
+    while let Some(c) = chars.next() {
+        let next = chars.peek().copied();
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            ' '..='~' => out.push(c),
+            '\u{7f}' => out.push_str("\\DEL"),
+            c if (c as u32) < 32 => {
+                out.push('\\');
+                out.push_str(NAMES[c as usize]);
+                // `\SO` followed by `H` would read as `\SOH`
+                if c == '\u{e}' && next == Some('H') {
+                    out.push_str("\\&");
+                }
+            }
+            c => {
+                out.push_str(&format!("\\{}", c as u32));
+                // A digit right after would read as part of the number
+                if next.is_some_and(|n| n.is_ascii_digit()) {
+                    out.push_str("\\&");
+                }
+            }
+        }
+    }
+    out.push('"');
+    out
+}
+
+fn text(s: &str) -> Haskell {
+    Haskell::Str(s.to_string())
+}
+
+fn attr<T>(item: &HTMLItem<T>) -> Haskell {
+    Haskell::Tuple(vec![
+        text(&item.ident),
+        Haskell::List(item.classes.iter().map(|c| text(c)).collect()),
+        Haskell::List(
+            item.attrs
+                .iter()
+                .map(|(k, v)| Haskell::Tuple(vec![text(k), text(v)]))
+                .collect(),
+        ),
+    ])
+}
+
+fn format(name: &str) -> Haskell {
+    Haskell::Con("Format", vec![text(name)])
+}
+
+fn meta(value: &MetaValue) -> Haskell {
+    match value {
+        MetaValue::Map(map) => Haskell::Con("MetaMap", vec![from_list(map.iter())]),
+        MetaValue::List(items) => Haskell::Con(
+            "MetaList",
+            vec![Haskell::List(items.iter().map(meta).collect())],
+        ),
+        MetaValue::Bool(true) => Haskell::Con("MetaBool", vec![Haskell::Con("True", vec![])]),
+        MetaValue::Bool(false) => Haskell::Con("MetaBool", vec![Haskell::Con("False", vec![])]),
+        MetaValue::Inlines(i) => Haskell::Con("MetaInlines", vec![inlines(i)]),
+    }
+}
+
+fn from_list<'a>(entries: impl Iterator<Item = (&'a String, &'a MetaValue)>) -> Haskell {
+    Haskell::Con(
+        "fromList",
+        vec![Haskell::List(
+            entries
+                .map(|(k, v)| Haskell::Tuple(vec![text(k), meta(v)]))
+                .collect(),
+        )],
+    )
+}
+
+fn blocks(blocks: &[Block]) -> Haskell {
+    Haskell::List(blocks.iter().filter_map(block).collect())
+}
+
+fn items(items: &[Vec<Block>]) -> Haskell {
+    Haskell::List(items.iter().map(|i| blocks(i)).collect())
+}
+
+fn block(block: &Block) -> Option<Haskell> {
+    let con = Haskell::Con;
+    Some(match block {
+        Block::Plain(i) => con("Plain", vec![inlines(i)]),
+        Block::Para(i) => con("Para", vec![inlines(i)]),
+        Block::CodeBlock(item) => con("CodeBlock", vec![attr(item), text(&item.children)]),
+        Block::RawBlock(f, t) => con("RawBlock", vec![format(f), text(t)]),
+        Block::BlockQuote(b) => con("BlockQuote", vec![blocks(b)]),
+        Block::OrderedList((start, delim), list) => con(
+            "OrderedList",
+            vec![
+                Haskell::Tuple(vec![
+                    Haskell::Int(*start),
+                    con("Decimal", vec![]),
+                    con(delim_name(*delim), vec![]),
+                ]),
+                items(list),
+            ],
+        ),
+        Block::BulletList(list) => con("BulletList", vec![items(list)]),
+        Block::Header(level, item) => con(
+            "Header",
+            vec![
+                Haskell::Int(*level as u64),
+                attr(item),
+                inlines(&item.children),
+            ],
+        ),
+        Block::HorizontalRule => con("HorizontalRule", vec![]),
+        Block::Table(t) => table(t),
+        Block::Div(item) => con("Div", vec![attr(item), blocks(&item.children)]),
+        Block::Callout(_) | Block::Comment(_) => return None,
+    })
+}
+
+fn table(table: &TableTag) -> Haskell {
+    let con = Haskell::Con;
+    let none = || attr(&HTMLItem::new(()));
+    let row = |cells: &[Vec<Inline>]| {
+        con(
+            "Row",
+            vec![
+                none(),
+                Haskell::List(
+                    cells
+                        .iter()
+                        .map(|c| {
+                            con(
+                                "Cell",
+                                vec![
+                                    none(),
+                                    con("AlignDefault", vec![]),
+                                    con("RowSpan", vec![Haskell::Int(1)]),
+                                    con("ColSpan", vec![Haskell::Int(1)]),
+                                    Haskell::List(vec![con("Plain", vec![inlines(c)])]),
+                                ],
+                            )
+                        })
+                        .collect(),
+                ),
+            ],
+        )
+    };
+    con(
+        "Table",
+        vec![
+            none(),
+            con(
+                "Caption",
+                vec![con("Nothing", vec![]), Haskell::List(vec![])],
+            ),
+            Haskell::List(
+                table
+                    .aligns
+                    .iter()
+                    .map(|a| {
+                        Haskell::Tuple(vec![
+                            con(alignment_name(*a), vec![]),
+                            con("ColWidthDefault", vec![]),
+                        ])
+                    })
+                    .collect(),
+            ),
+            con(
+                "TableHead",
+                vec![none(), Haskell::List(vec![row(&table.head)])],
+            ),
+            Haskell::List(vec![con(
+                "TableBody",
+                vec![
+                    none(),
+                    con("RowHeadColumns", vec![Haskell::Int(0)]),
+                    Haskell::List(vec![]),
+                    Haskell::List(table.rows.iter().map(|r| row(r)).collect()),
+                ],
+            )]),
+            con("TableFoot", vec![none(), Haskell::List(vec![])]),
+        ],
+    )
+}
+
+fn inlines(inlines: &[Inline]) -> Haskell {
+    Haskell::List(inlines.iter().map(inline).collect())
+}
+
+fn inline(inline: &Inline) -> Haskell {
+    let con = Haskell::Con;
+    let target = |(url, title): &(String, String)| Haskell::Tuple(vec![text(url), text(title)]);
+    match inline {
+        Inline::Str(s) => con("Str", vec![text(s)]),
+        Inline::Emph(i) => con("Emph", vec![inlines(i)]),
+        Inline::Strong(i) => con("Strong", vec![inlines(i)]),
+        Inline::Strikeout(i) => con("Strikeout", vec![inlines(i)]),
+        Inline::Code(item) => con("Code", vec![attr(item), text(&item.children)]),
+        Inline::Space => con("Space", vec![]),
+        Inline::SoftBreak => con("SoftBreak", vec![]),
+        Inline::LineBreak => con("LineBreak", vec![]),
+        Inline::Math(MathItem::Display(m)) => {
+            con("Math", vec![con("DisplayMath", vec![]), text(m)])
+        }
+        Inline::Math(MathItem::Inline(m)) => con("Math", vec![con("InlineMath", vec![]), text(m)]),
+        Inline::RawInline(f, t) => con("RawInline", vec![format(f), text(t)]),
+        Inline::Link(item, t) => con("Link", vec![attr(item), inlines(&item.children), target(t)]),
+        Inline::Image(item, t) => con(
+            "Image",
+            vec![attr(item), inlines(&item.children), target(t)],
+        ),
+        Inline::Note(b) => con("Note", vec![blocks(b)]),
+        Inline::Span(item) => con("Span", vec![attr(item), inlines(&item.children)]),
+        other => con(
+            "Str",
+            vec![text(&document::stringify(std::slice::from_ref(other)))],
+        ),
+    }
+}
+
+impl Pandoc {
+    /// The document as `pandoc -t native` writes it: only the blocks, or
+    /// with `standalone` the whole `Pandoc` value with its metadata as `-s` does.
+    pub fn to_native(&self, standalone: bool) -> String {
+        let body = blocks(&self.blocks);
+        let value = match standalone {
+            true => Haskell::Con(
+                "Pandoc",
+                vec![
+                    Haskell::Record("Meta", "unMeta", Box::new(from_list(self.meta.iter()))),
+                    body,
+                ],
+            ),
+            false => body,
+        };
+        let mut out = String::new();
+        value.render(0, false, &mut out);
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn show_escapes_like_haskell() {
+        assert_eq!(show("say \"hi\" \\o/"), r#""say \"hi\" \\o/""#);
+        assert_eq!(show("a\tb\nc\u{7f}"), r#""a\tb\nc\DEL""#);
+        assert_eq!(show("\u{e}H \u{e}I"), r#""\SO\&H \SOI""#);
+        assert_eq!(show("café"), r#""caf\233""#);
+        assert_eq!(show("é1 éa"), r#""\233\&1 \233a""#);
+        assert_eq!(show("جاوي"), r#""\1580\1575\1608\1610""#);
+    }
+
+    #[test]
+    fn long_values_break_across_lines() {
+        let words = |n: usize| {
+            Haskell::List(
+                (0..n)
+                    .map(|i| Haskell::Con("Str", vec![text(&format!("w{}", i))]))
+                    .collect(),
+            )
+        };
+        let mut out = String::new();
+        Haskell::Con("Para", vec![words(2)]).render(0, false, &mut out);
+        assert_eq!(out, r#"Para [ Str "w0" , Str "w1" ]"#);
+
+        let mut out = String::new();
+        Haskell::Con("Para", vec![words(8)]).render(0, false, &mut out);
+        let lines: Vec<&str> = out.lines().collect();
+        assert_eq!(lines[0], "Para");
+        assert_eq!(lines[1], r#"  [ Str "w0""#);
+        assert_eq!(lines[2], r#"  , Str "w1""#);
+        assert_eq!(lines.last(), Some(&"  ]"));
+        assert!(lines.iter().all(|l| l.chars().count() <= WIDTH));
+    }
+}
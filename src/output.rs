@@ -0,0 +1,169 @@
+use crate::value::Value;
+use clap::ValueEnum;
+use colored::Colorize;
+use serde::ser::{Serialize, SerializeMap, Serializer};
+use std::io::{Error, Write};
+
+#[derive(ValueEnum, Clone, Copy)]
+pub enum Format {
+    /// Aligned columns with colors for the terminal
+    Table,
+    /// An array with one object per row
+    Json,
+    /// One JSON object per line
+    Ndjson,
+    Csv,
+    Tsv,
+    MarkdownTable,
+}
+
+/// Rows of values under named columns, printable in any [Format].
+pub struct Table {
+    pub headers: Vec<String>,
+    pub rows: Vec<Vec<Value>>,
+}
+
+/// One row as a JSON object, with the keys in column order.
+struct Row<'a> {
+    headers: &'a [String],
+    values: &'a [Value],
+}
+
+impl Serialize for Row<'_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut map = serializer.serialize_map(Some(self.headers.len()))?;
+        for (header, value) in self.headers.iter().zip(self.values) {
+            map.serialize_entry(header, value)?;
+        }
+        map.end()
+    }
+}
+
+impl Table {
+    pub fn write(&self, format: Format, out: &mut impl Write) -> Result<(), Error> {
+        match format {
+            Format::Table => self.write_table(out),
+            Format::Json => {
+                let rows: Vec<Row> = self
+                    .rows
+                    .iter()
+                    .map(|values| Row {
+                        headers: &self.headers,
+                        values,
+                    })
+                    .collect();
+                let json = serde_json::to_string_pretty(&rows).map_err(Error::other)?;
+                writeln!(out, "{}", json)
+            }
+            Format::Ndjson => {
+                for values in &self.rows {
+                    let row = Row {
+                        headers: &self.headers,
+                        values,
+                    };
+                    writeln!(
+                        out,
+                        "{}",
+                        serde_json::to_string(&row).map_err(Error::other)?
+                    )?;
+                }
+                Ok(())
+            }
+            Format::MarkdownTable => self.write_markdown(out),
+            Format::Csv => self.write_separated(out, ",", csv_field),
+            Format::Tsv => self.write_separated(out, "\t", |s| s.replace(['\t', '\n', '\r'], " ")),
+        }
+    }
+
+    fn write_table(&self, out: &mut impl Write) -> Result<(), Error> {
+        let cells: Vec<Vec<String>> = self
+            .rows
+            .iter()
+            .map(|row| row.iter().map(cell_text).collect())
+            .collect();
+        let widths: Vec<usize> = (0..self.headers.len())
+            .map(|c| {
+                cells
+                    .iter()
+                    .map(|row| row[c].chars().count())
+                    .chain([self.headers[c].chars().count()])
+                    .max()
+                    .unwrap_or(0)
+            })
+            .collect();
+        let header: Vec<String> = self
+            .headers
+            .iter()
+            .zip(&widths)
+            .map(|(h, w)| format!("{:<w$}", h, w = w).bold().to_string())
+            .collect();
+        writeln!(out, "{}", header.join("  ").trim_end())?;
+        let rule: Vec<String> = widths.iter().map(|w| "─".repeat(*w)).collect();
+        writeln!(out, "{}", rule.join("  ").dimmed())?;
+        for (row, texts) in self.rows.iter().zip(&cells) {
+            let line: Vec<String> = row
+                .iter()
+                .zip(texts)
+                .zip(&widths)
+                .map(|((value, text), w)| {
+                    // Pad before coloring, so the escape codes do not count as width
+                    let padded = format!("{:<w$}", text, w = w);
+                    match value {
+                        Value::Date(_) | Value::DateTime(_) => padded.yellow(),
+                        Value::Link(_) => padded.blue(),
+                        Value::Number(_) => padded.cyan(),
+                        Value::Bool(_) => padded.magenta(),
+                        Value::Null => padded.dimmed(),
+                        _ => padded.normal(),
+                    }
+                    .to_string()
+                })
+                .collect();
+            writeln!(out, "{}", line.join("  ").trim_end())?;
+        }
+        Ok(())
+    }
+
+    fn write_separated(
+        &self,
+        out: &mut impl Write,
+        separator: &str,
+        escape: impl Fn(&str) -> String,
+    ) -> Result<(), Error> {
+        let header: Vec<String> = self.headers.iter().map(|h| escape(h)).collect();
+        writeln!(out, "{}", header.join(separator))?;
+        for row in &self.rows {
+            let fields: Vec<String> = row.iter().map(|v| escape(&v.to_string())).collect();
+            writeln!(out, "{}", fields.join(separator))?;
+        }
+        Ok(())
+    }
+
+    fn write_markdown(&self, out: &mut impl Write) -> Result<(), Error> {
+        let escape = |s: &str| s.replace('|', "\\|").replace(['\n', '\r'], " ");
+        let header: Vec<String> = self.headers.iter().map(|h| escape(h)).collect();
+        writeln!(out, "| {} |", header.join(" | "))?;
+        writeln!(out, "|{}", " --- |".repeat(self.headers.len()))?;
+        for row in &self.rows {
+            let cells: Vec<String> = row.iter().map(|v| escape(&v.to_string())).collect();
+            writeln!(out, "| {} |", cells.join(" | "))?;
+        }
+        Ok(())
+    }
+}
+
+fn cell_text(value: &Value) -> String {
+    match value {
+        Value::Null => "-".into(),
+        other => other.to_string(),
+    }
+}
+
+/// Quote a CSV field when it holds a separator, quote or line break.
+fn csv_field(field: &str) -> String {
+    if field.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.into()
+    }
+}
@@ -0,0 +1,691 @@
+use crate::document::{
+    self, Alignment, BlockElement as Block, Callout, DocumentAST, HTMLItem,
+    InlineElement as Inline, ListDelim, MathItem, TableTag,
+};
+use crate::links::Link;
+use crate::resolve::{Resolution, Resolver};
+use crate::value::{Metadata, Value};
+use crate::{FileData, Vault};
+use serde_json::{json, Value as Json};
+use std::cell::RefCell;
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+/// The version of pandoc-types the output is written for
+const API_VERSION: [u32; 3] = [1, 23, 1];
+
+/// Embeds of these files are shown as images, any other embed as a link
+const IMAGE_EXTENSIONS: [&str; 8] = ["png", "jpg", "jpeg", "gif", "svg", "webp", "bmp", "avif"];
+
+/// Fields the vault derives from the body rather than reads from the note
+const DERIVED_FIELDS: [&str; 2] = ["links", "hashtag"];
+
+/// A metadata value as pandoc models it.
+#[derive(Debug, Clone, PartialEq)]
+pub enum MetaValue {
+    Map(BTreeMap<String, MetaValue>),
+    List(Vec<MetaValue>),
+    Bool(bool),
+    Inlines(Vec<Inline>),
+}
+
+/// A whole document in pandoc's terms, `Pandoc Meta [Block]`.
+///
+/// Unlike a [DocumentAST] it holds no Obsidian elements, only what pandoc
+/// itself knows, so every writer can hand it to pandoc as it is.
+pub struct Pandoc {
+    pub meta: BTreeMap<String, MetaValue>,
+    pub blocks: Vec<Block>,
+}
+
+/// Rewrites the Obsidian elements of a note into pandoc elements:
+///
+/// - wikilinks become links to the relative path of the note they resolve to,
+///   or a `wikilink unresolved` span when they do not resolve
+/// - image embeds become images, other embeds links with the `embed` class
+/// - tags, highlights and fields become spans with the classes `tag`, `mark`
+///   and `field`, block ids empty spans with that identifier
+/// - callouts become `callout` divs with a `callout-title` div first
+/// - comments are dropped, as Obsidian does not show them
+pub struct Lowering<'a> {
+    resolver: Resolver,
+    note: &'a FileData,
+    /// The note links are relative to, which inside an embed is the embedded note
+    from: RefCell<PathBuf>,
+}
+
+impl<'a> Lowering<'a> {
+    pub fn new(vault: &Vault, note: &'a FileData) -> Self {
+        Self {
+            resolver: vault.resolver(),
+            note,
+            from: RefCell::new(note.path.clone()),
+        }
+    }
+
+    pub fn lower(&self, doc: DocumentAST) -> Pandoc {
+        Pandoc {
+            meta: self.meta(&doc.meta),
+            blocks: self.blocks(doc.content),
+        }
+    }
+
+    /// Nested frontmatter comes back as maps, and a note without a `title`
+    /// gets its name, as Obsidian shows it.
+    fn meta(&self, metadata: &Metadata) -> BTreeMap<String, MetaValue> {
+        let mut meta = BTreeMap::new();
+        let mut keys: Vec<&String> = metadata.keys().collect();
+        keys.sort();
+        for key in keys {
+            if DERIVED_FIELDS.contains(&key.as_str()) {
+                continue;
+            }
+            if let Some(value) = self.meta_value(&metadata[key]) {
+                insert_meta(&mut meta, key, value);
+            }
+        }
+        let name = self.note.path.file_stem().unwrap_or_default();
+        meta.entry("title".into())
+            .or_insert_with(|| MetaValue::Inlines(document::words(&name.to_string_lossy())));
+        meta
+    }
+
+    fn meta_value(&self, value: &Value) -> Option<MetaValue> {
+        Some(match value {
+            Value::Null => return None,
+            Value::Bool(b) => MetaValue::Bool(*b),
+            Value::List(items) => {
+                MetaValue::List(items.iter().filter_map(|v| self.meta_value(v)).collect())
+            }
+            Value::Link(link) => MetaValue::Inlines(self.wikilink(link, "wikilink")),
+            Value::Text(text) => MetaValue::Inlines(document::words(text)),
+            other => MetaValue::Inlines(vec![Inline::Str(other.to_string())]),
+        })
+    }
+
+    fn blocks(&self, blocks: Vec<Block>) -> Vec<Block> {
+        blocks.into_iter().filter_map(|b| self.block(b)).collect()
+    }
+
+    fn block(&self, block: Block) -> Option<Block> {
+        Some(match block {
+            Block::Plain(i) => Block::Plain(self.inlines(i)),
+            Block::Para(i) => Block::Para(self.inlines(i)),
+            Block::BlockQuote(b) => Block::BlockQuote(self.blocks(b)),
+            Block::OrderedList(start, items) => {
+                Block::OrderedList(start, items.into_iter().map(|i| self.blocks(i)).collect())
+            }
+            Block::BulletList(items) => {
+                Block::BulletList(items.into_iter().map(|i| self.blocks(i)).collect())
+            }
+            Block::Header(level, item) => Block::Header(
+                level,
+                HTMLItem {
+                    children: self.inlines(item.children),
+                    ..item
+                },
+            ),
+            Block::Table(table) => {
+                let cells = |row: Vec<Vec<Inline>>| -> Vec<Vec<Inline>> {
+                    row.into_iter().map(|c| self.inlines(c)).collect()
+                };
+                Block::Table(TableTag {
+                    head: cells(table.head),
+                    rows: table.rows.into_iter().map(cells).collect(),
+                    ..table
+                })
+            }
+            Block::Callout(callout) => self.callout(callout),
+            Block::Div(item) => {
+                // Embedded content, see `embed::Transclusion`
+                let embedded = item.attrs.iter().find(|(k, _)| k == "note");
+                let outer = embedded.map(|(_, path)| self.from.replace(path.into()));
+                let children = self.blocks(item.children);
+                if let Some(outer) = outer {
+                    self.from.replace(outer);
+                }
+                Block::Div(HTMLItem { children, ..item })
+            }
+            // Pandoc keeps raw blocks without the line break that ends them
+            Block::RawBlock(format, text) => {
+                Block::RawBlock(format, text.trim_end_matches('\n').into())
+            }
+            Block::Comment(_) => return None,
+            other => other,
+        })
+    }
+
+    fn callout(&self, callout: Callout) -> Block {
+        let title = self.inlines(callout.shown_title());
+        let mut children = vec![Block::Div(HTMLItem {
+            classes: vec!["callout-title".into()],
+            ..HTMLItem::new(vec![Block::Plain(title)])
+        })];
+        children.extend(self.blocks(callout.content));
+        let mut attrs = vec![("callout".to_string(), callout.kind.clone())];
+        attrs.extend(callout.fold.map(|f| ("fold".to_string(), f.to_string())));
+        Block::Div(HTMLItem {
+            classes: vec!["callout".into(), format!("callout-{}", callout.kind)],
+            attrs,
+            ..HTMLItem::new(children)
+        })
+    }
+
+    fn inlines(&self, inlines: Vec<Inline>) -> Vec<Inline> {
+        document::tidy(inlines.into_iter().flat_map(|i| self.inline(i)).collect())
+    }
+
+    fn inline(&self, inline: Inline) -> Vec<Inline> {
+        vec![match inline {
+            Inline::Emph(i) => Inline::Emph(self.inlines(i)),
+            Inline::Strong(i) => Inline::Strong(self.inlines(i)),
+            Inline::Strikeout(i) => Inline::Strikeout(self.inlines(i)),
+            Inline::Link(item, (url, title)) => Inline::Link(
+                HTMLItem {
+                    children: self.inlines(item.children),
+                    ..item
+                },
+                (self.embedded_url(url), title),
+            ),
+            Inline::Image(item, target) => Inline::Image(
+                HTMLItem {
+                    children: self.inlines(item.children),
+                    ..item
+                },
+                target,
+            ),
+            Inline::Span(item) => Inline::Span(HTMLItem {
+                children: self.inlines(item.children),
+                ..item
+            }),
+            Inline::Note(blocks) => Inline::Note(self.blocks(blocks)),
+            Inline::WikiLink(link) => return self.wikilink(&link, "wikilink"),
+            Inline::Embed(link) => return self.embed(&link),
+            Inline::Tag(tag) => span(&["tag"], vec![Inline::Str(tag)]),
+            Inline::Highlight(i) => span(&["mark"], self.inlines(i)),
+            Inline::Comment(_) => return Vec::new(),
+            Inline::BlockId(id) => Inline::Span(HTMLItem {
+                ident: id,
+                ..HTMLItem::new(Vec::new())
+            }),
+            Inline::Field(key, value) => {
+                let mut children = vec![Inline::Str(format!("{}:", key)), Inline::Space];
+                children.extend(document::words(&value));
+                Inline::Span(HTMLItem {
+                    classes: vec!["field".into()],
+                    attrs: vec![("key".into(), key)],
+                    ..HTMLItem::new(document::tidy(children))
+                })
+            }
+            // The boxes pandoc itself reads task lists into
+            Inline::Task(' ') => Inline::Str("☐".into()),
+            Inline::Task('x' | 'X') => Inline::Str("☒".into()),
+            Inline::Task(status) => Inline::Str(format!("[{}]", status)),
+            other => other,
+        }]
+    }
+
+    fn wikilink(&self, link: &Link, class: &str) -> Vec<Inline> {
+        let text = document::words(&document::link_text(link));
+        match self.url(link) {
+            Some(url) => vec![Inline::Link(
+                HTMLItem {
+                    classes: vec![class.into()],
+                    ..HTMLItem::new(text)
+                },
+                (url, String::new()),
+            )],
+            None => vec![span(&[class, "unresolved"], text)],
+        }
+    }
+
+    /// Images are shown, sized by `![[image.png|300]]` or `|300x200`, and
+    /// anything else is linked to.
+    fn embed(&self, link: &Link) -> Vec<Inline> {
+        let image = Path::new(&link.target)
+            .extension()
+            .and_then(|e| e.to_str())
+            .is_some_and(|e| IMAGE_EXTENSIONS.contains(&e.to_lowercase().as_str()));
+        let url = match (image, self.url(link)) {
+            (true, Some(url)) => url,
+            _ => return self.wikilink(link, "embed"),
+        };
+        let mut item = HTMLItem::new(Vec::new());
+        if let Some(alias) = &link.alias {
+            let (width, height) = alias.split_once('x').unwrap_or((alias, ""));
+            let digits = |s: &str| s.chars().all(|c| c.is_ascii_digit());
+            if !width.is_empty() && digits(width) && digits(height) {
+                item.attrs.push(("width".into(), width.into()));
+                if !height.is_empty() {
+                    item.attrs.push(("height".into(), height.into()));
+                }
+            } else {
+                item.children = document::words(alias);
+            }
+        }
+        vec![Inline::Image(item, (url, String::new()))]
+    }
+
+    /// A markdown link inside an embed points from the embedded note, so it
+    /// is made relative to the note being written instead.
+    fn embedded_url(&self, url: String) -> String {
+        let inside_embed = *self.from.borrow() != self.note.path;
+        // Headings of the same page and URLs with a scheme stay as they are
+        if !inside_embed || url.starts_with('#') || url.contains(':') {
+            return url;
+        }
+        self.url(&Link::markdown(&url)).unwrap_or(url)
+    }
+
+    /// Where a link points to, relative to the folder of the note, with the
+    /// heading as the identifier pandoc gives it.
+    ///
+    /// Links inside an embed resolve from the embedded note, but the URL is
+    /// still relative to the note being written.
+    fn url(&self, link: &Link) -> Option<String> {
+        let path = match self.resolver.resolve(link, &self.from.borrow()) {
+            Resolution::Resolved(path) => path,
+            _ => return None,
+        };
+        let fragment = match (&link.heading, &link.block) {
+            (Some(heading), _) => format!("#{}", document::ident(heading)),
+            (None, Some(block)) => format!("#{}", block),
+            (None, None) => String::new(),
+        };
+        if path == self.note.path && !fragment.is_empty() {
+            return Some(fragment);
+        }
+        let folder = self.note.path.parent().unwrap_or(Path::new(""));
+        let relative: Vec<String> = relative(&path, folder)
+            .components()
+            .map(|c| encode(&c.as_os_str().to_string_lossy()))
+            .collect();
+        Some(relative.join("/") + &fragment)
+    }
+}
+
+fn span(classes: &[&str], children: Vec<Inline>) -> Inline {
+    Inline::Span(HTMLItem {
+        classes: classes.iter().map(|c| c.to_string()).collect(),
+        ..HTMLItem::new(children)
+    })
+}
+
+/// Put `a.b.c` keys of flattened frontmatter back into nested maps.
+fn insert_meta(meta: &mut BTreeMap<String, MetaValue>, key: &str, value: MetaValue) {
+    if let Some((head, rest)) = key.split_once('.') {
+        let entry = meta
+            .entry(head.to_string())
+            .or_insert_with(|| MetaValue::Map(BTreeMap::new()));
+        if let MetaValue::Map(map) = entry {
+            insert_meta(map, rest, value);
+            return;
+        }
+    }
+    meta.insert(key.to_string(), value);
+}
+
+/// The path of a file as seen from a folder, both relative to the vault root.
+fn relative(path: &Path, folder: &Path) -> PathBuf {
+    let common = path
+        .components()
+        .zip(folder.components())
+        .take_while(|(a, b)| a == b)
+        .count();
+    let mut relative: PathBuf = folder.components().skip(common).map(|_| "..").collect();
+    relative.extend(path.components().skip(common));
+    relative
+}
+
+/// Escape the characters of a file name that would break a URL.
+fn encode(name: &str) -> String {
+    name.chars()
+        .map(|c| match c {
+            ' ' | '#' | '?' | '%' | '(' | ')' => format!("%{:02X}", c as u32),
+            c => c.to_string(),
+        })
+        .collect()
+}
+
+impl Pandoc {
+    /// The document in the JSON `pandoc -f json` reads.
+    pub fn to_json(&self) -> Json {
+        let meta: serde_json::Map<String, Json> = self
+            .meta
+            .iter()
+            .map(|(k, v)| (k.clone(), meta_json(v)))
+            .collect();
+        json!({
+            "pandoc-api-version": API_VERSION,
+            "meta": meta,
+            "blocks": blocks_json(&self.blocks),
+        })
+    }
+}
+
+/// An element with content, `{"t": "Para", "c": [...]}`
+fn node(t: &str, c: Json) -> Json {
+    json!({ "t": t, "c": c })
+}
+
+/// An element without content, `{"t": "Space"}`
+fn tag(t: &str) -> Json {
+    json!({ "t": t })
+}
+
+fn attr_json<T>(item: &HTMLItem<T>) -> Json {
+    json!([item.ident, item.classes, item.attrs])
+}
+
+fn meta_json(value: &MetaValue) -> Json {
+    match value {
+        MetaValue::Map(map) => node(
+            "MetaMap",
+            Json::Object(map.iter().map(|(k, v)| (k.clone(), meta_json(v))).collect()),
+        ),
+        MetaValue::List(items) => node("MetaList", items.iter().map(meta_json).collect()),
+        MetaValue::Bool(b) => node("MetaBool", json!(b)),
+        MetaValue::Inlines(inlines) => node("MetaInlines", inlines_json(inlines)),
+    }
+}
+
+pub fn alignment_name(align: Alignment) -> &'static str {
+    match align {
+        Alignment::Left => "AlignLeft",
+        Alignment::Right => "AlignRight",
+        Alignment::Center => "AlignCenter",
+        Alignment::Default => "AlignDefault",
+    }
+}
+
+pub fn delim_name(delim: ListDelim) -> &'static str {
+    match delim {
+        ListDelim::Period => "Period",
+        ListDelim::OneParen => "OneParen",
+    }
+}
+
+fn blocks_json(blocks: &[Block]) -> Json {
+    blocks.iter().filter_map(block_json).collect()
+}
+
+fn items_json(items: &[Vec<Block>]) -> Json {
+    items.iter().map(|i| blocks_json(i)).collect()
+}
+
+fn block_json(block: &Block) -> Option<Json> {
+    Some(match block {
+        Block::Plain(i) => node("Plain", inlines_json(i)),
+        Block::Para(i) => node("Para", inlines_json(i)),
+        Block::CodeBlock(item) => node("CodeBlock", json!([attr_json(item), item.children])),
+        Block::RawBlock(format, text) => node("RawBlock", json!([format, text])),
+        Block::BlockQuote(b) => node("BlockQuote", blocks_json(b)),
+        Block::OrderedList((start, delim), items) => node(
+            "OrderedList",
+            json!([
+                [start, tag("Decimal"), tag(delim_name(*delim))],
+                items_json(items)
+            ]),
+        ),
+        Block::BulletList(items) => node("BulletList", items_json(items)),
+        Block::Header(level, item) => node(
+            "Header",
+            json!([level, attr_json(item), inlines_json(&item.children)]),
+        ),
+        Block::HorizontalRule => tag("HorizontalRule"),
+        Block::Table(table) => table_json(table),
+        Block::Div(item) => node("Div", json!([attr_json(item), blocks_json(&item.children)])),
+        // Lowered before writing
+        Block::Callout(_) | Block::Comment(_) => return None,
+    })
+}
+
+/// A table in the shape of pandoc-types 1.23: attributes, caption, column
+/// specs, head, bodies and foot.
+fn table_json(table: &TableTag) -> Json {
+    let none = json!(["", [], []]);
+    let row = |cells: &[Vec<Inline>]| {
+        let cells: Vec<Json> = cells
+            .iter()
+            .map(|c| {
+                json!([
+                    none,
+                    tag("AlignDefault"),
+                    1,
+                    1,
+                    [node("Plain", inlines_json(c))]
+                ])
+            })
+            .collect();
+        json!([none, cells])
+    };
+    let specs: Vec<Json> = table
+        .aligns
+        .iter()
+        .map(|a| json!([tag(alignment_name(*a)), tag("ColWidthDefault")]))
+        .collect();
+    let rows: Vec<Json> = table.rows.iter().map(|r| row(r)).collect();
+    node(
+        "Table",
+        json!([
+            none,
+            [null, []],
+            specs,
+            [none, [row(&table.head)]],
+            [[none, 0, [], rows]],
+            [none, []]
+        ]),
+    )
+}
+
+fn inlines_json(inlines: &[Inline]) -> Json {
+    inlines.iter().map(inline_json).collect()
+}
+
+fn inline_json(inline: &Inline) -> Json {
+    match inline {
+        Inline::Str(s) => node("Str", json!(s)),
+        Inline::Emph(i) => node("Emph", inlines_json(i)),
+        Inline::Strong(i) => node("Strong", inlines_json(i)),
+        Inline::Strikeout(i) => node("Strikeout", inlines_json(i)),
+        Inline::Code(item) => node("Code", json!([attr_json(item), item.children])),
+        Inline::Space => tag("Space"),
+        Inline::SoftBreak => tag("SoftBreak"),
+        Inline::LineBreak => tag("LineBreak"),
+        Inline::Math(MathItem::Display(m)) => node("Math", json!([tag("DisplayMath"), m])),
+        Inline::Math(MathItem::Inline(m)) => node("Math", json!([tag("InlineMath"), m])),
+        Inline::RawInline(format, text) => node("RawInline", json!([format, text])),
+        Inline::Link(item, (url, title)) => node(
+            "Link",
+            json!([attr_json(item), inlines_json(&item.children), [url, title]]),
+        ),
+        Inline::Image(item, (url, title)) => node(
+            "Image",
+            json!([attr_json(item), inlines_json(&item.children), [url, title]]),
+        ),
+        Inline::Note(blocks) => node("Note", blocks_json(blocks)),
+        Inline::Span(item) => node(
+            "Span",
+            json!([attr_json(item), inlines_json(&item.children)]),
+        ),
+        // Lowered before writing, so only their text is left here
+        other => node(
+            "Str",
+            json!(document::stringify(std::slice::from_ref(other))),
+        ),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn vault() -> Vault {
+        let note = |path: &str| FileData {
+            path: path.into(),
+            ..Default::default()
+        };
+        Vault {
+            notes: vec![note("a/Note.md"), note("b/My Other.md")],
+            attachments: vec!["img/pic.png".into()],
+        }
+    }
+
+    fn lower(vault: &Vault, body: &str, meta: Metadata) -> Pandoc {
+        Lowering::new(vault, &vault.notes[0]).lower(DocumentAST::parse(body, meta))
+    }
+
+    #[test]
+    fn json_document_shape() {
+        let vault = vault();
+        let json = lower(&vault, "| a | b |\n|:--|--:|\n| 1 | 2 |\n", Metadata::new()).to_json();
+        assert_eq!(json["pandoc-api-version"], json!([1, 23, 1]));
+        assert_eq!(
+            json["meta"],
+            json!({ "title": { "t": "MetaInlines", "c": [{ "t": "Str", "c": "Note" }] } })
+        );
+        let cell = |text: &str| {
+            json!([
+                ["", [], []],
+                { "t": "AlignDefault" },
+                1,
+                1,
+                [{ "t": "Plain", "c": [{ "t": "Str", "c": text }] }]
+            ])
+        };
+        assert_eq!(
+            json["blocks"],
+            json!([{
+                "t": "Table",
+                "c": [
+                    ["", [], []],
+                    [null, []],
+                    [
+                        [{ "t": "AlignLeft" }, { "t": "ColWidthDefault" }],
+                        [{ "t": "AlignRight" }, { "t": "ColWidthDefault" }]
+                    ],
+                    [["", [], []], [[["", [], []], [cell("a"), cell("b")]]]],
+                    [[["", [], []], 0, [], [[["", [], []], [cell("1"), cell("2")]]]]],
+                    [["", [], []], []]
+                ]
+            }])
+        );
+    }
+
+    #[test]
+    fn wikilinks_become_relative_links() {
+        let vault = vault();
+        let doc = lower(
+            &vault,
+            "[[My Other#Some Part|there]] [[#Top]] [[Missing]] ![[pic.png|300]]",
+            Metadata::new(),
+        );
+        let link = |classes: &[&str], text: &str, url: &str| {
+            json!({ "t": "Link", "c": [
+                ["", classes, []],
+                [{ "t": "Str", "c": text }],
+                [url, ""]
+            ]})
+        };
+        assert_eq!(
+            doc.to_json()["blocks"][0]["c"],
+            json!([
+                link(&["wikilink"], "there", "../b/My%20Other.md#some-part"),
+                { "t": "Space" },
+                link(&["wikilink"], "Top", "#top"),
+                { "t": "Space" },
+                { "t": "Span", "c": [
+                    ["", ["wikilink", "unresolved"], []],
+                    [{ "t": "Str", "c": "Missing" }]
+                ]},
+                { "t": "Space" },
+                { "t": "Image", "c": [["", [], [["width", "300"]]], [], ["../img/pic.png", ""]] }
+            ])
+        );
+    }
+
+    #[test]
+    fn nested_meta() {
+        let vault = vault();
+        let meta: Metadata = [
+            ("project.status", Value::Text("in progress".into())),
+            ("project.owner.name", Value::Text("Siti".into())),
+            ("draft", Value::Bool(true)),
+            ("up", Value::Link(Link::wiki("My Other"))),
+            (
+                "tags",
+                Value::List(vec![Value::Text("#a".into()), Value::Null]),
+            ),
+            ("links", Value::List(Vec::new())),
+        ]
+        .into_iter()
+        .map(|(k, v)| (k.to_string(), v))
+        .collect();
+        let doc = lower(&vault, "", meta);
+        let words = |text: &str| MetaValue::Inlines(document::words(text));
+        let map = |entries: Vec<(&str, MetaValue)>| {
+            MetaValue::Map(entries.into_iter().map(|(k, v)| (k.into(), v)).collect())
+        };
+        let expected = map(vec![
+            ("draft", MetaValue::Bool(true)),
+            (
+                "project",
+                map(vec![
+                    ("owner", map(vec![("name", words("Siti"))])),
+                    ("status", words("in progress")),
+                ]),
+            ),
+            ("tags", MetaValue::List(vec![words("#a")])),
+            ("title", words("Note")),
+            (
+                "up",
+                MetaValue::Inlines(vec![Inline::Link(
+                    HTMLItem {
+                        classes: vec!["wikilink".into()],
+                        ..HTMLItem::new(document::words("My Other"))
+                    },
+                    ("../b/My%20Other.md".into(), String::new()),
+                )]),
+            ),
+        ]);
+        assert_eq!(MetaValue::Map(doc.meta), expected);
+    }
+
+    #[test]
+    fn links_in_embeds_resolve_from_the_embedded_note() {
+        let note = |path: &str| FileData {
+            path: path.into(),
+            ..Default::default()
+        };
+        let vault = Vault {
+            notes: vec![
+                note("Root.md"),
+                note("deep/E.md"),
+                note("deep/Sib.md"),
+                note("Sib.md"),
+            ],
+            attachments: Vec::new(),
+        };
+        let embedded = DocumentAST::parse(
+            "[sib](Sib.md) [[Sib]] [web](https://x.org)",
+            Metadata::new(),
+        );
+        let doc = DocumentAST {
+            content: vec![Block::Div(HTMLItem {
+                classes: vec!["embed".into()],
+                attrs: vec![("note".into(), "deep/E.md".into())],
+                ..HTMLItem::new(embedded.content)
+            })],
+            ..Default::default()
+        };
+        let lowered = Lowering::new(&vault, &vault.notes[0]).lower(doc).to_json();
+        let urls: Vec<&Json> = lowered["blocks"][0]["c"][1][0]["c"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .filter(|i| i["t"] == "Link")
+            .map(|l| &l["c"][2][0])
+            .collect();
+        assert_eq!(urls, ["deep/Sib.md", "deep/Sib.md", "https://x.org"]);
+    }
+}
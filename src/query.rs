@@ -0,0 +1,111 @@
+use crate::dql::{self, QueryKind};
+use crate::output::{Format, Table};
+use crate::value::Value;
+use crate::{read_vault, Command, FileData, VaultArgs};
+use clap::Args;
+use colored::Colorize;
+use std::io::{Error, ErrorKind, Write};
+
+/// Run a Dataview-style query over the notes of the vault
+///
+/// e.g. `TABLE status, due FROM #project WHERE due < date(today) SORT due DESC LIMIT 5`
+#[derive(Args)]
+pub struct Query {
+    #[command(flatten)]
+    vault: VaultArgs,
+    /// `LIST|TABLE fields FROM #tag or "folder" WHERE expr SORT field DESC LIMIT n`
+    query: String,
+    #[arg(long, value_enum, default_value_t = Format::Table)]
+    format: Format,
+}
+
+impl Command for Query {
+    fn execute(&self) -> Result<(), Error> {
+        let query = dql::parse(&self.query)
+            .map_err(|e| Error::new(ErrorKind::InvalidInput, e.to_string()))?;
+        let vault = read_vault(&self.vault)?;
+        let mut notes: Vec<&FileData> = vault
+            .notes
+            .iter()
+            .filter(|n| query.from.as_ref().is_none_or(|f| f.matches(n)))
+            .filter(|n| query.filter.as_ref().is_none_or(|f| f.test(n)))
+            .collect();
+        let descending: Vec<bool> = query.sort.iter().map(|(_, d)| *d).collect();
+        dql::sort_by_keys(&mut notes, &descending, |n| {
+            query.sort.iter().map(|(e, _)| e.eval(n)).collect()
+        });
+        if let Some(limit) = query.limit {
+            notes.truncate(limit);
+        }
+
+        let file = |n: &FileData| Value::Text(n.path.display().to_string());
+        let mut out = std::io::stdout().lock();
+        let table = match &query.kind {
+            QueryKind::List(expr) => {
+                if let Format::Table = self.format {
+                    for n in &notes {
+                        match expr {
+                            Some(e) => writeln!(
+                                out,
+                                "- {}: {}",
+                                n.path.with_extension("").display().to_string().blue(),
+                                e.eval(n)
+                            )?,
+                            None => writeln!(
+                                out,
+                                "- {}",
+                                n.path.with_extension("").display().to_string().blue()
+                            )?,
+                        }
+                    }
+                    writeln!(out, "---")?;
+                    writeln!(out, "{} results", notes.len())?;
+                    return Ok(());
+                }
+                let mut headers = vec!["File".to_string()];
+                headers.extend(expr.as_ref().map(|_| "Value".to_string()));
+                Table {
+                    headers,
+                    rows: notes
+                        .iter()
+                        .map(|n| {
+                            let mut row = vec![file(n)];
+                            row.extend(expr.as_ref().map(|e| e.eval(n)));
+                            row
+                        })
+                        .collect(),
+                }
+            }
+            QueryKind::Table {
+                columns,
+                without_id,
+            } => {
+                let mut headers: Vec<String> = Vec::new();
+                if !without_id {
+                    headers.push("File".into());
+                }
+                headers.extend(columns.iter().map(|(_, name)| name.clone()));
+                Table {
+                    headers,
+                    rows: notes
+                        .iter()
+                        .map(|n| {
+                            let mut row = Vec::new();
+                            if !without_id {
+                                row.push(file(n));
+                            }
+                            row.extend(columns.iter().map(|(e, _)| e.eval(n)));
+                            row
+                        })
+                        .collect(),
+                }
+            }
+        };
+        table.write(self.format, &mut out)?;
+        if let Format::Table = self.format {
+            writeln!(out, "---")?;
+            writeln!(out, "{} results", notes.len())?;
+        }
+        Ok(())
+    }
+}
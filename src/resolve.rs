@@ -0,0 +1,154 @@
+use crate::links::{Link, LinkKind};
+use crate::FileData;
+use std::collections::HashMap;
+use std::path::{Component, Path, PathBuf};
+
+/// Where a link points to once it is looked up in the vault.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Resolution {
+    /// The file the link points to, relative to the vault root
+    Resolved(PathBuf),
+    /// More than one file matches and the link does not say which one
+    Ambiguous(Vec<PathBuf>),
+    Unresolved,
+}
+
+/// Resolves link targets to files the way Obsidian does.
+///
+/// 1. An empty target, as in `[[#Heading]]`, points to the note it is in.
+/// 2. A target with a `/` is a path, tried relative to the linking note, then
+///    from the vault root, then as the end of a longer path.
+/// 3. A bare name matches the file of that name anywhere in the vault. When
+///    several files share the name, the one in the folder of the linking note
+///    wins, otherwise the link is ambiguous.
+/// 4. Otherwise the `aliases` of the notes are tried.
+///
+/// A target without an extension is a markdown note. All matching ignores case.
+pub struct Resolver {
+    /// Lowercase path of every file, to its real path
+    paths: HashMap<String, PathBuf>,
+    /// Lowercase file name, to every file with that name
+    names: HashMap<String, Vec<PathBuf>>,
+    aliases: HashMap<String, Vec<PathBuf>>,
+}
+
+impl Resolver {
+    pub fn new(notes: &[FileData], attachments: &[PathBuf]) -> Self {
+        let mut resolver = Self {
+            paths: HashMap::new(),
+            names: HashMap::new(),
+            aliases: HashMap::new(),
+        };
+        for path in notes.iter().map(|n| &n.path).chain(attachments) {
+            resolver.paths.insert(key(path), path.clone());
+            if let Some(name) = path.file_name() {
+                resolver
+                    .names
+                    .entry(name.to_string_lossy().to_lowercase())
+                    .or_default()
+                    .push(path.clone());
+            }
+        }
+        for note in notes {
+            for alias in &note.aliases {
+                resolver
+                    .aliases
+                    .entry(alias.to_lowercase())
+                    .or_default()
+                    .push(note.path.clone());
+            }
+        }
+        resolver
+    }
+
+    /// Resolve a link found in the note at `from`. External links are never resolved.
+    pub fn resolve(&self, link: &Link, from: &Path) -> Resolution {
+        if link.kind == LinkKind::External {
+            return Resolution::Unresolved;
+        }
+        let target = link.target.trim().trim_start_matches('/');
+        if target.is_empty() {
+            return Resolution::Resolved(from.to_path_buf());
+        }
+        let folder = from.parent().unwrap_or(Path::new(""));
+        for candidate in with_extension(target) {
+            let resolution = if link.kind == LinkKind::Markdown || candidate.contains('/') {
+                self.by_path(&candidate, folder)
+            } else {
+                self.by_name(&candidate, folder)
+            };
+            if resolution != Resolution::Unresolved {
+                return resolution;
+            }
+        }
+        match self.aliases.get(&target.to_lowercase()) {
+            Some(paths) => pick(paths.clone(), folder),
+            None => Resolution::Unresolved,
+        }
+    }
+
+    fn by_path(&self, target: &str, folder: &Path) -> Resolution {
+        let relative = normalize(&folder.join(target));
+        for path in [relative, normalize(Path::new(target))] {
+            if let Some(found) = self.paths.get(&key(&path)) {
+                return Resolution::Resolved(found.clone());
+            }
+        }
+        let suffix = format!("/{}", target.to_lowercase());
+        let matches: Vec<PathBuf> = self
+            .paths
+            .iter()
+            .filter(|(k, _)| k.ends_with(&suffix))
+            .map(|(_, p)| p.clone())
+            .collect();
+        pick(matches, folder)
+    }
+
+    fn by_name(&self, target: &str, folder: &Path) -> Resolution {
+        match self.names.get(&target.to_lowercase()) {
+            Some(paths) => pick(paths.clone(), folder),
+            None => Resolution::Unresolved,
+        }
+    }
+}
+
+fn pick(mut paths: Vec<PathBuf>, folder: &Path) -> Resolution {
+    paths.sort();
+    paths.dedup();
+    match paths.len() {
+        0 => Resolution::Unresolved,
+        1 => Resolution::Resolved(paths.remove(0)),
+        _ => match paths.iter().find(|p| p.parent() == Some(folder)) {
+            Some(p) => Resolution::Resolved(p.clone()),
+            None => Resolution::Ambiguous(paths),
+        },
+    }
+}
+
+/// The names a target may have on disk, `Note` is tried as `Note.md` first.
+fn with_extension(target: &str) -> Vec<String> {
+    if target.to_lowercase().ends_with(".md") {
+        vec![target.into()]
+    } else {
+        vec![format!("{}.md", target), target.into()]
+    }
+}
+
+fn key(path: &Path) -> String {
+    path.to_string_lossy().replace('\\', "/").to_lowercase()
+}
+
+/// Resolve `.` and `..` without touching the filesystem.
+fn normalize(path: &Path) -> PathBuf {
+    let mut out = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::ParentDir => {
+                out.pop();
+            }
+            Component::Normal(c) => out.push(c),
+            Component::CurDir | Component::RootDir | Component::Prefix(_) => {}
+        }
+    }
+    out
+}
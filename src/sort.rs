@@ -0,0 +1,65 @@
+use crate::dql;
+use crate::value::Value;
+use crate::FileData;
+use clap::Args;
+
+/// Order of the notes of `list` and `read`, by date unless `--sort` is given
+#[derive(Args, Clone)]
+pub struct SortArgs {
+    /// Keys to sort by in order of importance, e.g. `links:desc,name`.
+    /// `name`, `path`, `date`, `links`, `mtime` and `size` describe the file,
+    /// any other key is a metadata field. Add `:desc` to sort a key from high to low.
+    #[arg(long, value_delimiter = ',')]
+    sort: Vec<String>,
+    /// Reverse the order of every sort key
+    #[arg(long)]
+    reverse: bool,
+}
+
+/// The value of a note to sort on.
+fn key(note: &FileData, name: &str) -> Value {
+    match name {
+        "name" => Value::Text(note.name.clone()),
+        "path" => Value::Text(note.path.display().to_string()),
+        "date" | "mtime" | "size" => dql::field(note, &format!("file.{}", name)),
+        "links" => Value::Number(note.links.iter().filter(|l| l.is_internal()).count() as f64),
+        other => dql::field(note, other),
+    }
+}
+
+impl SortArgs {
+    /// The flags that give this order, to repeat it in another command.
+    pub fn args(&self) -> Vec<String> {
+        let mut args = Vec::new();
+        if !self.sort.is_empty() {
+            args.push(format!("--sort \"{}\"", self.sort.join(",")));
+        }
+        if self.reverse {
+            args.push("--reverse".into());
+        }
+        args
+    }
+
+    /// Sort the notes by the keys, then by path so that ties always come out
+    /// in the same order.
+    pub fn apply(&self, notes: &mut [&FileData]) {
+        let mut keys: Vec<(&str, bool)> = self
+            .sort
+            .iter()
+            .map(|k| match k.rsplit_once(':') {
+                Some((k, "desc")) => (k, true),
+                Some((k, "asc")) => (k, false),
+                _ => (k.as_str(), false),
+            })
+            .collect();
+        if keys.is_empty() {
+            keys.push(("date", false));
+        }
+        // Notes of equal keys stay in path order, as the sort is stable
+        notes.sort_by(|a, b| a.path.cmp(&b.path));
+        let descending: Vec<bool> = keys.iter().map(|(_, d)| d ^ self.reverse).collect();
+        dql::sort_by_keys(notes, &descending, |n| {
+            keys.iter().map(|(k, _)| key(n, k)).collect()
+        });
+    }
+}
@@ -0,0 +1,163 @@
+use crate::{read_vault, Command, VaultArgs};
+use clap::{Args, ValueEnum};
+use colored::Colorize;
+use std::collections::{BTreeMap, BTreeSet};
+use std::io::Error;
+
+/// Show the nested tag hierarchy with usage counts, or the notes of one tag
+#[derive(Args)]
+pub struct Tags {
+    #[command(flatten)]
+    vault: VaultArgs,
+    /// List the notes with this tag or any tag nested below it, e.g. `project/alpha`
+    #[arg(long)]
+    tag: Option<String>,
+    #[arg(long, value_enum, default_value_t = TagSort::Count)]
+    sort: TagSort,
+}
+
+#[derive(ValueEnum, Clone, Copy)]
+enum TagSort {
+    /// Most used tags first
+    Count,
+    Name,
+}
+
+/// Whether `tag` is `wanted` or a tag nested below it, e.g. `#Project/alpha`
+/// is within `project`. Case and the leading `#` do not matter.
+pub fn is_within(tag: &str, wanted: &str) -> bool {
+    let tag = tag.trim_start_matches('#').to_lowercase();
+    let wanted = wanted
+        .trim_start_matches('#')
+        .trim_end_matches('/')
+        .to_lowercase();
+    tag.strip_prefix(&wanted)
+        .is_some_and(|rest| rest.is_empty() || rest.starts_with('/'))
+}
+
+/// One level of a nested tag such as `#project/alpha/design`.
+///
+/// Tags are matched ignoring case, like Obsidian does, and shown the way they
+/// were first written.
+#[derive(Default)]
+struct TagNode {
+    name: String,
+    /// Notes tagged with exactly this tag
+    notes: BTreeSet<usize>,
+    children: BTreeMap<String, TagNode>,
+}
+
+impl TagNode {
+    fn insert(&mut self, tag: &str, note: usize) {
+        let mut node = self;
+        for part in tag
+            .trim_start_matches('#')
+            .split('/')
+            .filter(|p| !p.is_empty())
+        {
+            node = node
+                .children
+                .entry(part.to_lowercase())
+                .or_insert_with(|| TagNode {
+                    name: part.into(),
+                    ..Default::default()
+                });
+        }
+        node.notes.insert(note);
+    }
+
+    /// Notes tagged with this tag or any tag below it.
+    fn all_notes(&self) -> BTreeSet<usize> {
+        let mut notes = self.notes.clone();
+        for child in self.children.values() {
+            notes.extend(child.all_notes());
+        }
+        notes
+    }
+
+    fn sorted_children(&self, sort: TagSort) -> Vec<(&TagNode, usize)> {
+        let mut children: Vec<(&TagNode, usize)> = self
+            .children
+            .values()
+            .map(|c| (c, c.all_notes().len()))
+            .collect();
+        if let TagSort::Count = sort {
+            // `children` is already in name order, so ties stay alphabetical
+            children.sort_by_key(|c| std::cmp::Reverse(c.1));
+        }
+        children
+    }
+
+    fn print(&self, count: usize, prefix: &str, depth: usize, sort: TagSort) {
+        let name = if depth == 0 {
+            format!("#{}", self.name)
+        } else {
+            format!("{}/{}", prefix, self.name)
+        };
+        let direct = if self.notes.len() != count {
+            format!(" ({} direct)", self.notes.len())
+                .dimmed()
+                .to_string()
+        } else {
+            String::new()
+        };
+        println!(
+            "{}{} {}{}",
+            "  ".repeat(depth),
+            name.underline(),
+            format!("[{}]", count).yellow(),
+            direct
+        );
+        for (child, count) in self.sorted_children(sort) {
+            child.print(count, &name, depth + 1, sort);
+        }
+    }
+}
+
+impl Command for Tags {
+    fn execute(&self) -> Result<(), Error> {
+        let vault = read_vault(&self.vault)?;
+        let mut root = TagNode::default();
+        for (i, note) in vault.notes.iter().enumerate() {
+            for tag in &note.hashtags {
+                root.insert(tag, i);
+            }
+        }
+        match &self.tag {
+            Some(tag) => {
+                let notes: Vec<usize> = (0..vault.notes.len())
+                    .filter(|&i| vault.notes[i].hashtags.iter().any(|t| is_within(t, tag)))
+                    .collect();
+                println!(
+                    "{} notes tagged #{}",
+                    notes.len(),
+                    tag.trim_start_matches('#')
+                );
+                // Note indices follow the date order of `list`
+                for (n, &i) in notes.iter().enumerate() {
+                    println!("[{: >2}] {}", n + 1, vault.notes[i]);
+                }
+            }
+            None => {
+                for (child, count) in root.sorted_children(self.sort) {
+                    child.print(count, "", 0, self.sort);
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn nested_tags() {
+        assert!(is_within("#project", "project"));
+        assert!(is_within("#Project/Alpha", "#project"));
+        assert!(is_within("#project/alpha/design", "project/alpha/"));
+        assert!(!is_within("#projects", "project"));
+        assert!(!is_within("#project", "project/alpha"));
+    }
+}
@@ -0,0 +1,219 @@
+use crate::date::{self, parse_date_arg};
+use crate::items::ListItem;
+use crate::tags;
+use crate::{read_vault, Command, FileData, VaultArgs};
+use chrono::NaiveDate;
+use clap::{Args, ValueEnum};
+use colored::Colorize;
+use lazy_static::lazy_static;
+use regex::Regex;
+use std::collections::BTreeMap;
+use std::io::Error;
+
+lazy_static! {
+    static ref EMOJI_DATE_RE: Regex = Regex::new(
+        r"(?x)
+        (?P<emoji>📅|⏳|🛫|✅|➕)️?   # Tasks plugin date markers
+        \s*(?P<date>\d{4}-\d{2}-\d{2})
+        ",
+    )
+    .expect("Regex run error");
+}
+
+/// List tasks from every note, filtered by status, due date, tag or note
+#[derive(Args)]
+pub struct Tasks {
+    #[command(flatten)]
+    vault: VaultArgs,
+    /// `todo`, `done`, `in-progress`, `cancelled` or the status character itself
+    #[arg(long, value_delimiter = ',')]
+    status: Vec<String>,
+    /// Only tasks due on or after this date
+    #[arg(long, value_parser = parse_date_arg)]
+    due_after: Option<NaiveDate>,
+    /// Only tasks due on or before this date
+    #[arg(long, value_parser = parse_date_arg)]
+    due_before: Option<NaiveDate>,
+    /// Only tasks with this tag or a tag nested below it
+    #[arg(long)]
+    tag: Option<String>,
+    /// Only tasks from notes whose path matches this regex
+    #[arg(long)]
+    note: Option<String>,
+    #[arg(long, value_enum, default_value_t = TaskGroup::Note)]
+    group_by: TaskGroup,
+}
+
+#[derive(ValueEnum, Clone, Copy)]
+enum TaskGroup {
+    Note,
+    Due,
+}
+
+/// Dates a task can carry, from Tasks plugin emoji or inline fields.
+#[derive(Default)]
+struct TaskDates {
+    /// `📅 2024-03-01` or `due:: 2024-03-01`
+    due: Option<NaiveDate>,
+    /// `⏳ 2024-03-01` or `scheduled::`
+    scheduled: Option<NaiveDate>,
+    /// `🛫 2024-03-01` or `start::`
+    start: Option<NaiveDate>,
+    /// `✅ 2024-03-01` or `completion::`
+    done: Option<NaiveDate>,
+}
+
+impl TaskDates {
+    fn of(item: &ListItem) -> Self {
+        let mut dates = Self::default();
+        for c in EMOJI_DATE_RE.captures_iter(&item.text) {
+            let d = date::parse_date(&c["date"]);
+            match &c["emoji"] {
+                "📅" => dates.due = d,
+                "⏳" => dates.scheduled = d,
+                "🛫" => dates.start = d,
+                "✅" => dates.done = d,
+                _ => {}
+            }
+        }
+        let field = |key: &str| {
+            item.fields
+                .get(key)
+                .and_then(|v| NaiveDate::try_from(v).ok())
+        };
+        dates.due = dates.due.or_else(|| field("due"));
+        dates.scheduled = dates.scheduled.or_else(|| field("scheduled"));
+        dates.start = dates.start.or_else(|| field("start"));
+        dates.done = dates.done.or_else(|| field("completion"));
+        dates
+    }
+}
+
+/// Map a status name to the character written in the checkbox.
+fn status_chars(name: &str) -> Vec<char> {
+    match name {
+        "todo" => vec![' '],
+        "done" => vec!['x', 'X'],
+        "in-progress" => vec!['/'],
+        "cancelled" => vec!['-'],
+        other => other.chars().take(1).collect(),
+    }
+}
+
+fn format_task(item: &ListItem, dates: &TaskDates, note: &FileData) -> String {
+    let status = item.status.unwrap_or(' ');
+    let checkbox = format!("[{}]", status);
+    let checkbox = match status {
+        'x' | 'X' => checkbox.green(),
+        '-' => checkbox.dimmed(),
+        ' ' => checkbox.normal(),
+        _ => checkbox.cyan(),
+    };
+    let today = chrono::Local::now().date_naive();
+    let due = match dates.due {
+        Some(d) if d < today && !matches!(status, 'x' | 'X' | '-') => {
+            format!(" 📅 {}", d).red().to_string()
+        }
+        Some(d) => format!(" 📅 {}", d).yellow().to_string(),
+        None => String::new(),
+    };
+    let mut extra = Vec::new();
+    if let Some(d) = dates.start {
+        extra.push(format!("🛫 {}", d));
+    }
+    if let Some(d) = dates.scheduled {
+        extra.push(format!("⏳ {}", d));
+    }
+    if let Some(d) = dates.done {
+        extra.push(format!("✅ {}", d));
+    }
+    let text = EMOJI_DATE_RE.replace_all(&item.text, "");
+    let text = text.split_whitespace().collect::<Vec<&str>>().join(" ");
+    let extra = if extra.is_empty() {
+        String::new()
+    } else {
+        format!(" {}", extra.join(" ")).dimmed().to_string()
+    };
+    format!(
+        "{} {}{}{} {}",
+        checkbox,
+        text,
+        due,
+        extra,
+        format!("({}:{})", note.path.display(), item.line).dimmed()
+    )
+}
+
+impl Command for Tasks {
+    fn execute(&self) -> Result<(), Error> {
+        let vault = read_vault(&self.vault)?;
+        let note_pattern = match &self.note {
+            Some(p) => Some(Regex::new(p).map_err(Error::other)?),
+            None => None,
+        };
+        let statuses: Vec<char> = self.status.iter().flat_map(|s| status_chars(s)).collect();
+        let mut tasks: Vec<(&FileData, &ListItem, TaskDates)> = Vec::new();
+        for note in &vault.notes {
+            if let Some(p) = &note_pattern {
+                if !p.is_match(&note.path.to_string_lossy()) {
+                    continue;
+                }
+            }
+            for item in note.items.iter().filter(|i| i.status.is_some()) {
+                if !statuses.is_empty() && !statuses.contains(&item.status.unwrap_or(' ')) {
+                    continue;
+                }
+                if let Some(tag) = &self.tag {
+                    if !item.tags.iter().any(|t| tags::is_within(t, tag)) {
+                        continue;
+                    }
+                }
+                let dates = TaskDates::of(item);
+                if self.due_after.is_some() || self.due_before.is_some() {
+                    let Some(due) = dates.due else { continue };
+                    if self.due_after.is_some_and(|d| due < d)
+                        || self.due_before.is_some_and(|d| due > d)
+                    {
+                        continue;
+                    }
+                }
+                tasks.push((note, item, dates));
+            }
+        }
+        match self.group_by {
+            TaskGroup::Note => {
+                let mut current = None;
+                for (note, item, dates) in &tasks {
+                    if current != Some(&note.path) {
+                        println!("{}", note.path.display().to_string().blue());
+                        current = Some(&note.path);
+                    }
+                    println!("  {}", format_task(item, dates, note));
+                }
+            }
+            TaskGroup::Due => {
+                let mut groups: BTreeMap<Option<NaiveDate>, Vec<String>> = BTreeMap::new();
+                for (note, item, dates) in &tasks {
+                    groups
+                        .entry(dates.due)
+                        .or_default()
+                        .push(format_task(item, dates, note));
+                }
+                // Tasks without a due date go last
+                let undated = groups.remove(&None);
+                for (due, lines) in groups.into_iter().chain(undated.map(|l| (None, l))) {
+                    match due {
+                        Some(d) => println!("{}", d.format("%Y-%m-%d %A").to_string().yellow()),
+                        None => println!("{}", "No due date".yellow()),
+                    }
+                    for line in lines {
+                        println!("  {}", line);
+                    }
+                }
+            }
+        }
+        println!("---");
+        println!("{} tasks", tasks.len());
+        Ok(())
+    }
+}
@@ -0,0 +1,509 @@
+use crate::document::{
+    self, Alignment, BlockElement as Block, Callout, DocumentAST, InlineElement as Inline,
+    ListDelim, MathItem, TableTag,
+};
+use colored::{Color, Colorize};
+use lazy_static::lazy_static;
+use regex::Regex;
+
+lazy_static! {
+    /// The escape codes `colored` wraps text in
+    static ref ESCAPE_RE: Regex = Regex::new(r"\x1b\[[0-9;]*m").expect("Regex run error");
+}
+
+/// Bullets of nested lists, one per level
+const BULLETS: [&str; 3] = ["•", "◦", "▪"];
+
+/// Text styles that add up as elements nest, as `colored` resets all of them
+/// at the end of each piece of text.
+#[derive(Clone, Copy, Default)]
+struct Style {
+    bold: bool,
+    italic: bool,
+    underline: bool,
+    strikethrough: bool,
+    dimmed: bool,
+    color: Option<Color>,
+    background: Option<Color>,
+}
+
+impl Style {
+    fn paint(&self, text: &str) -> String {
+        if text.is_empty() {
+            return String::new();
+        }
+        let mut painted = text.normal();
+        if self.bold {
+            painted = painted.bold();
+        }
+        if self.italic {
+            painted = painted.italic();
+        }
+        if self.underline {
+            painted = painted.underline();
+        }
+        if self.strikethrough {
+            painted = painted.strikethrough();
+        }
+        if self.dimmed {
+            painted = painted.dimmed();
+        }
+        if let Some(color) = self.color {
+            painted = painted.color(color);
+        }
+        if let Some(background) = self.background {
+            painted = painted.on_color(background);
+        }
+        painted.to_string()
+    }
+
+    fn color(self, color: Color) -> Self {
+        Self {
+            color: Some(color),
+            ..self
+        }
+    }
+}
+
+/// The width of text on screen, leaving out its escape codes.
+fn width(text: &str) -> usize {
+    ESCAPE_RE.replace_all(text, "").chars().count()
+}
+
+/// Put `first` before the first line and `rest` before the others.
+fn prefix(lines: Vec<String>, first: &str, rest: &str) -> Vec<String> {
+    lines
+        .into_iter()
+        .enumerate()
+        .map(|(n, line)| {
+            let prefix = if n == 0 { first } else { rest };
+            match line.is_empty() {
+                true => prefix.trim_end().to_string(),
+                false => format!("{}{}", prefix, line),
+            }
+        })
+        .collect()
+}
+
+/// The color Obsidian gives each type of callout.
+fn callout_color(kind: &str) -> Color {
+    match kind {
+        "tip" | "hint" | "important" | "success" | "check" | "done" => Color::Green,
+        "question" | "help" | "faq" | "warning" | "caution" | "attention" => Color::Yellow,
+        "failure" | "fail" | "missing" | "danger" | "error" | "bug" => Color::Red,
+        "example" => Color::Magenta,
+        "abstract" | "summary" | "tldr" | "quote" | "cite" => Color::Cyan,
+        _ => Color::Blue,
+    }
+}
+
+/// Renders a note for the terminal, with styled text, boxed code blocks,
+/// aligned tables and footnotes at the end.
+pub struct Terminal {
+    /// Columns of the terminal, for rules
+    width: usize,
+    /// How deep the lists being rendered are nested
+    depth: usize,
+    footnotes: Vec<Vec<String>>,
+}
+
+impl Terminal {
+    pub fn new(width: usize) -> Self {
+        Self {
+            width,
+            depth: 0,
+            footnotes: Vec::new(),
+        }
+    }
+
+    pub fn render(mut self, doc: &DocumentAST) -> String {
+        let mut lines = self.blocks(&doc.content);
+        if !self.footnotes.is_empty() {
+            lines.push(String::new());
+            lines.push("─".repeat(self.width.min(20)).dimmed().to_string());
+            for (n, footnote) in std::mem::take(&mut self.footnotes).into_iter().enumerate() {
+                let marker = format!("[{}] ", n + 1);
+                let indent = " ".repeat(marker.len());
+                lines.extend(prefix(footnote, &marker.cyan().to_string(), &indent));
+            }
+        }
+        lines.join("\n")
+    }
+
+    /// Blocks with a blank line between them, except after the text of a
+    /// tight list item.
+    fn blocks(&mut self, blocks: &[Block]) -> Vec<String> {
+        let mut lines = Vec::new();
+        let mut previous: Option<&Block> = None;
+        for block in blocks {
+            let rendered = self.block(block);
+            if rendered.is_empty() {
+                continue;
+            }
+            if previous.is_some_and(|p| !matches!(p, Block::Plain(_))) {
+                lines.push(String::new());
+            }
+            lines.extend(rendered);
+            previous = Some(block);
+        }
+        lines
+    }
+
+    fn block(&mut self, block: &Block) -> Vec<String> {
+        let text = |s: String| -> Vec<String> { s.split('\n').map(String::from).collect() };
+        match block {
+            Block::Plain(i) | Block::Para(i) => text(self.inlines(i, Style::default())),
+            Block::Header(level, item) => {
+                let (color, rule) = match level {
+                    1 => (Some(Color::Magenta), Some("═")),
+                    2 => (Some(Color::Blue), Some("─")),
+                    3 => (Some(Color::Cyan), None),
+                    4 => (Some(Color::Green), None),
+                    _ => (None, None),
+                };
+                let style = Style {
+                    bold: true,
+                    color,
+                    ..Style::default()
+                };
+                let heading = self.inlines(&item.children, style);
+                let mut lines = vec![heading.clone()];
+                lines.extend(rule.map(|r| style.paint(&r.repeat(width(&heading)))));
+                lines
+            }
+            Block::CodeBlock(item) => code_box(item.classes.first(), &item.children),
+            Block::RawBlock(_, raw) => raw.lines().map(|l| l.dimmed().to_string()).collect(),
+            Block::BlockQuote(blocks) => {
+                let bar = "│ ".dimmed().to_string();
+                let lines = self.blocks(blocks);
+                prefix(lines, &bar, &bar)
+            }
+            Block::Callout(callout) => self.callout(callout),
+            Block::Div(item) => match item.attrs.iter().find(|(k, _)| k == "source") {
+                // Embedded content, under the name of where it comes from
+                Some((_, source)) => {
+                    let mut lines = vec![format!("↪ {}", source).dimmed().italic().to_string()];
+                    lines.extend(self.blocks(&item.children));
+                    let bar = "┃ ".dimmed().to_string();
+                    prefix(lines, &bar, &bar)
+                }
+                None => self.blocks(&item.children),
+            },
+            Block::OrderedList((start, delim), items) => {
+                let delim = match delim {
+                    ListDelim::Period => '.',
+                    ListDelim::OneParen => ')',
+                };
+                let markers = (0..items.len())
+                    .map(|n| format!("{}{}", *start + n as u64, delim))
+                    .collect();
+                self.list(items, markers)
+            }
+            Block::BulletList(items) => {
+                let bullet = BULLETS[self.depth % BULLETS.len()];
+                self.list(items, vec![bullet.to_string(); items.len()])
+            }
+            Block::HorizontalRule => vec!["─".repeat(self.width).dimmed().to_string()],
+            Block::Table(table) => self.table(table),
+            // Obsidian does not show comments
+            Block::Comment(_) => Vec::new(),
+        }
+    }
+
+    /// Items with their markers right-aligned, and a blank line between loose items.
+    fn list(&mut self, items: &[Vec<Block>], markers: Vec<String>) -> Vec<String> {
+        let marker_width = markers.iter().map(|m| m.chars().count()).max().unwrap_or(0);
+        let loose = items
+            .iter()
+            .any(|i| i.iter().any(|b| matches!(b, Block::Para(_))));
+        let mut lines = Vec::new();
+        self.depth += 1;
+        for (n, (item, marker)) in items.iter().zip(markers).enumerate() {
+            if loose && n > 0 {
+                lines.push(String::new());
+            }
+            let marker = format!("{:>w$} ", marker, w = marker_width);
+            let indent = " ".repeat(marker_width + 1);
+            let item = match self.blocks(item) {
+                empty if empty.is_empty() => vec![String::new()],
+                item => item,
+            };
+            lines.extend(prefix(item, &marker.bold().to_string(), &indent));
+        }
+        self.depth -= 1;
+        lines
+    }
+
+    /// A bar in the color of the callout type, with the title in bold.
+    fn callout(&mut self, callout: &Callout) -> Vec<String> {
+        let style = Style {
+            bold: true,
+            ..Style::default()
+        }
+        .color(callout_color(&callout.kind));
+        let mut title = self.inlines(&callout.shown_title(), style);
+        match callout.fold {
+            Some('-') => title.push_str(&style.paint(" ▸")),
+            Some(_) => title.push_str(&style.paint(" ▾")),
+            None => {}
+        }
+        let mut lines = vec![title];
+        lines.extend(self.blocks(&callout.content));
+        let bar = style.paint("│ ");
+        prefix(lines, &bar, &bar)
+    }
+
+    /// Columns padded to their widest cell, in the alignment of the column.
+    fn table(&mut self, table: &TableTag) -> Vec<String> {
+        let bold = Style {
+            bold: true,
+            ..Style::default()
+        };
+        let mut rows: Vec<Vec<String>> = vec![table
+            .head
+            .iter()
+            .map(|c| self.inlines(c, bold).replace('\n', " "))
+            .collect()];
+        for row in &table.rows {
+            rows.push(
+                row.iter()
+                    .map(|c| self.inlines(c, Style::default()).replace('\n', " "))
+                    .collect(),
+            );
+        }
+        let widths: Vec<usize> = (0..table.aligns.len())
+            .map(|c| {
+                rows.iter()
+                    .filter_map(|r| r.get(c))
+                    .map(|cell| width(cell))
+                    .max()
+                    .unwrap_or(0)
+            })
+            .collect();
+        let line = |row: &[String]| -> String {
+            let cells: Vec<String> = widths
+                .iter()
+                .zip(&table.aligns)
+                .enumerate()
+                .map(|(c, (w, align))| {
+                    let cell = row.get(c).map(String::as_str).unwrap_or_default();
+                    let pad = w - width(cell);
+                    let (left, right) = match align {
+                        Alignment::Right => (pad, 0),
+                        Alignment::Center => (pad / 2, pad - pad / 2),
+                        Alignment::Left | Alignment::Default => (0, pad),
+                    };
+                    format!("{}{}{}", " ".repeat(left), cell, " ".repeat(right))
+                })
+                .collect();
+            cells
+                .join(&" │ ".dimmed().to_string())
+                .trim_end()
+                .to_string()
+        };
+        let rule: Vec<String> = widths.iter().map(|w| "─".repeat(*w)).collect();
+        let mut lines = vec![line(&rows[0]), rule.join("─┼─").dimmed().to_string()];
+        lines.extend(rows[1..].iter().map(|r| line(r)));
+        lines
+    }
+
+    fn inlines(&mut self, inlines: &[Inline], style: Style) -> String {
+        // Obsidian hides comments and block ids when reading, spaces around them too
+        let shown: Vec<Inline> = inlines
+            .iter()
+            .filter(|i| !matches!(i, Inline::Comment(_) | Inline::BlockId(_)))
+            .cloned()
+            .collect();
+        document::tidy(shown)
+            .iter()
+            .map(|i| self.inline(i, style))
+            .collect()
+    }
+
+    fn inline(&mut self, inline: &Inline, style: Style) -> String {
+        match inline {
+            Inline::Str(s) => style.paint(s),
+            Inline::Space => style.paint(" "),
+            Inline::SoftBreak | Inline::LineBreak => "\n".into(),
+            Inline::Emph(i) => self.inlines(
+                i,
+                Style {
+                    italic: true,
+                    ..style
+                },
+            ),
+            Inline::Strong(i) => self.inlines(
+                i,
+                Style {
+                    bold: true,
+                    ..style
+                },
+            ),
+            Inline::Strikeout(i) => self.inlines(
+                i,
+                Style {
+                    strikethrough: true,
+                    ..style
+                },
+            ),
+            Inline::Code(item) => style.color(Color::Yellow).paint(&item.children),
+            Inline::Math(MathItem::Display(m) | MathItem::Inline(m)) => Style {
+                italic: true,
+                ..style
+            }
+            .color(Color::Cyan)
+            .paint(m),
+            Inline::RawInline(_, raw) => Style {
+                dimmed: true,
+                ..style
+            }
+            .paint(raw),
+            Inline::Link(item, (url, _)) => {
+                let text = self.inlines(
+                    &item.children,
+                    Style {
+                        underline: true,
+                        ..style
+                    }
+                    .color(Color::Blue),
+                );
+                // Autolinks already show their address
+                match document::stringify(&item.children) == *url
+                    || item.classes.iter().any(|c| c == "uri" || c == "email")
+                {
+                    true => text,
+                    false => format!("{} {}", text, format!("({})", url).dimmed()),
+                }
+            }
+            Inline::Image(item, (url, _)) => {
+                let alt = document::stringify(&item.children);
+                format!("[image: {}]", alt.trim()).dimmed().to_string()
+                    + &format!(" ({})", url).dimmed().to_string()
+            }
+            Inline::Note(blocks) => {
+                let depth = std::mem::take(&mut self.depth);
+                let footnote = self.blocks(blocks);
+                self.depth = depth;
+                self.footnotes.push(footnote);
+                format!("[{}]", self.footnotes.len()).cyan().to_string()
+            }
+            Inline::Span(item) => self.inlines(&item.children, style),
+            Inline::WikiLink(link) => Style {
+                underline: true,
+                ..style
+            }
+            .color(Color::Blue)
+            .paint(&document::link_text(link)),
+            Inline::Embed(link) => Style {
+                underline: true,
+                dimmed: true,
+                ..style
+            }
+            .paint(&format!("↪ {}", document::link_text(link))),
+            Inline::Tag(tag) => style.color(Color::Cyan).paint(tag),
+            Inline::Highlight(i) => self.inlines(
+                i,
+                Style {
+                    background: Some(Color::Yellow),
+                    ..style
+                }
+                .color(Color::Black),
+            ),
+            Inline::Comment(_) | Inline::BlockId(_) => String::new(),
+            Inline::Field(key, value) => {
+                Style {
+                    bold: true,
+                    ..style
+                }
+                .paint(&format!("{}:", key))
+                    + &style.paint(&format!(" {}", value))
+            }
+            Inline::Task(' ') => style.paint("☐"),
+            Inline::Task('x' | 'X') => style.color(Color::Green).paint("☑"),
+            Inline::Task(status) => style.color(Color::Yellow).paint(&format!("[{}]", status)),
+        }
+    }
+}
+
+/// A code block in a box, with its language on the top edge.
+fn code_box(language: Option<&String>, code: &str) -> Vec<String> {
+    let code = code.trim_end_matches('\n').replace('\t', "    ");
+    let lines: Vec<&str> = code.split('\n').collect();
+    let label = language.map(|l| format!(" {} ", l)).unwrap_or_default();
+    let inner = lines
+        .iter()
+        .map(|l| l.chars().count())
+        .max()
+        .unwrap_or(0)
+        .max(label.chars().count());
+    let mut top = "╭─".dimmed().to_string() + &label.bold().to_string();
+    top += &"─"
+        .repeat(inner + 1 - label.chars().count())
+        .dimmed()
+        .to_string();
+    top += &"╮".dimmed().to_string();
+    let mut boxed = vec![top];
+    for line in lines {
+        boxed.push(format!(
+            "{} {}{} {}",
+            "│".dimmed(),
+            line,
+            " ".repeat(inner - line.chars().count()),
+            "│".dimmed()
+        ));
+    }
+    boxed.push(format!("╰{}╯", "─".repeat(inner + 2)).dimmed().to_string());
+    boxed
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::value::Metadata;
+
+    fn render(body: &str) -> String {
+        colored::control::set_override(false);
+        Terminal::new(30).render(&DocumentAST::parse(body, Metadata::new()))
+    }
+
+    #[test]
+    fn headings_are_underlined_to_their_width() {
+        assert_eq!(
+            render("# Tajuk *utama*\n\n## Dua\n\n### Tiga\n"),
+            "Tajuk utama\n═══════════\n\nDua\n───\n\nTiga"
+        );
+    }
+
+    #[test]
+    fn code_boxes_carry_their_language() {
+        assert_eq!(
+            render("```rust\nfn main() {}\n```\n"),
+            "╭─ rust ───────╮\n│ fn main() {} │\n╰──────────────╯"
+        );
+        assert_eq!(render("```\nx\n```\n"), "╭───╮\n│ x │\n╰───╯");
+    }
+
+    #[test]
+    fn tables_are_aligned() {
+        assert_eq!(
+            render("| a | bb | c |\n|:--|--:|:-:|\n| long | 1 | mid |\n"),
+            "a    │ bb │  c\n─────┼────┼────\nlong │  1 │ mid"
+        );
+    }
+
+    #[test]
+    fn lists_callouts_and_footnotes() {
+        assert_eq!(
+            render("- [x] done\n- item\n  1. one\n\n> [!tip]-\n> body[^1]\n\n[^1]: note\n"),
+            "• ☑ done\n• item\n  1. one\n\n│ Tip ▸\n│ body[1]\n\n────────────────────\n[1] note"
+        );
+    }
+
+    #[test]
+    fn raw_html_passes_through() {
+        assert_eq!(
+            render("<details>\n<b>x</b>\n</details>\n\nA <kbd>Ctrl</kbd> key"),
+            "<details>\n<b>x</b>\n</details>\n\nA <kbd>Ctrl</kbd> key"
+        );
+    }
+}
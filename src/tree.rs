@@ -0,0 +1,215 @@
+use crate::links::Link;
+use crate::resolve::Resolution;
+use crate::{read_vault, Command, FileData, VaultArgs};
+use clap::Args;
+use colored::Colorize;
+use std::collections::{HashMap, HashSet};
+use std::io::Error;
+use std::path::Path;
+
+/// Show the parent/child hierarchy built from the parents field (`idx-naik`)
+#[derive(Args)]
+pub struct Tree {
+    #[command(flatten)]
+    vault: VaultArgs,
+    /// Start from this note instead of every top-level note
+    #[arg(long)]
+    start: Option<String>,
+    /// How many levels below the starting notes to show
+    #[arg(long)]
+    depth: Option<usize>,
+}
+
+/// Parent/child links between notes, by index into the notes of the vault.
+struct Hierarchy<'a> {
+    notes: &'a [FileData],
+    parents: Vec<Vec<usize>>,
+    children: Vec<Vec<usize>>,
+}
+
+impl<'a> Hierarchy<'a> {
+    fn new(notes: &'a [FileData], resolve: impl Fn(&str, &Path) -> Resolution) -> Self {
+        let index: HashMap<&Path, usize> = notes
+            .iter()
+            .enumerate()
+            .map(|(i, n)| (n.path.as_path(), i))
+            .collect();
+        let mut parents = vec![Vec::new(); notes.len()];
+        let mut children = vec![Vec::new(); notes.len()];
+        for (i, note) in notes.iter().enumerate() {
+            for parent in &note.parents {
+                if let Resolution::Resolved(p) = resolve(parent, &note.path) {
+                    if let Some(&j) = index.get(p.as_path()) {
+                        if !parents[i].contains(&j) {
+                            parents[i].push(j);
+                            children[j].push(i);
+                        }
+                    }
+                }
+            }
+        }
+        Self {
+            notes,
+            parents,
+            children,
+        }
+    }
+
+    /// Number of notes below `node`, each counted once.
+    fn descendants(&self, node: usize) -> usize {
+        let mut seen = HashSet::new();
+        let mut stack = vec![node];
+        while let Some(n) = stack.pop() {
+            for &c in &self.children[n] {
+                if c != node && seen.insert(c) {
+                    stack.push(c);
+                }
+            }
+        }
+        seen.len()
+    }
+
+    /// Every cycle in the hierarchy, each listed once starting from its first note.
+    fn cycles(&self) -> Vec<Vec<usize>> {
+        fn visit(
+            h: &Hierarchy,
+            node: usize,
+            state: &mut Vec<u8>,
+            stack: &mut Vec<usize>,
+            found: &mut Vec<Vec<usize>>,
+        ) {
+            state[node] = 1;
+            stack.push(node);
+            for &c in &h.children[node] {
+                match state[c] {
+                    0 => visit(h, c, state, stack, found),
+                    1 => {
+                        let start = stack.iter().position(|&s| s == c).unwrap_or(0);
+                        let mut cycle = stack[start..].to_vec();
+                        let min = cycle.iter().enumerate().min_by_key(|(_, &n)| n);
+                        let first = min.map(|(i, _)| i).unwrap_or(0);
+                        cycle.rotate_left(first);
+                        if !found.contains(&cycle) {
+                            found.push(cycle);
+                        }
+                    }
+                    _ => {}
+                }
+            }
+            stack.pop();
+            state[node] = 2;
+        }
+        let mut state = vec![0; self.notes.len()];
+        let mut found = Vec::new();
+        for n in 0..self.notes.len() {
+            if state[n] == 0 {
+                visit(self, n, &mut state, &mut Vec::new(), &mut found);
+            }
+        }
+        found
+    }
+
+    fn label(&self, node: usize) -> String {
+        let note = &self.notes[node];
+        note.path.with_extension("").display().to_string()
+    }
+
+    fn print(
+        &self,
+        node: usize,
+        prefix: &str,
+        last: bool,
+        path: &mut Vec<usize>,
+        depth: Option<usize>,
+    ) {
+        let (branch, next) = match (path.is_empty(), last) {
+            (true, _) => ("", ""),
+            (false, true) => ("└── ", "    "),
+            (false, false) => ("├── ", "│   "),
+        };
+        let mut line = format!("{}{}{}", prefix, branch, self.label(node).blue());
+        if path.contains(&node) {
+            println!("{} {}", line, "↻ cycle".red());
+            return;
+        }
+        let count = self.descendants(node);
+        if count > 0 {
+            line = format!("{} {}", line, format!("[{}]", count).yellow());
+        }
+        if self.parents[node].len() > 1 {
+            line = format!(
+                "{} {}",
+                line,
+                format!("({} parents)", self.parents[node].len()).dimmed()
+            );
+        }
+        println!("{}", line);
+        if depth.is_some_and(|d| path.len() >= d) {
+            return;
+        }
+        path.push(node);
+        let prefix = format!("{}{}", prefix, next);
+        let children = &self.children[node];
+        for (i, &c) in children.iter().enumerate() {
+            self.print(c, &prefix, i + 1 == children.len(), path, depth);
+        }
+        path.pop();
+    }
+}
+
+impl Command for Tree {
+    fn execute(&self) -> Result<(), Error> {
+        let vault = read_vault(&self.vault)?;
+        let resolver = vault.resolver();
+        let hierarchy = Hierarchy::new(&vault.notes, |parent, from| {
+            resolver.resolve(&Link::wiki(parent), from)
+        });
+        let roots: Vec<usize> = match &self.start {
+            Some(name) => {
+                let path = &vault.find(name)?.path;
+                vault
+                    .notes
+                    .iter()
+                    .position(|n| &n.path == path)
+                    .into_iter()
+                    .collect()
+            }
+            None => (0..vault.notes.len())
+                .filter(|&n| hierarchy.parents[n].is_empty() && !hierarchy.children[n].is_empty())
+                .collect(),
+        };
+        for root in roots {
+            hierarchy.print(root, "", true, &mut Vec::new(), self.depth);
+        }
+
+        let cycles = hierarchy.cycles();
+        if !cycles.is_empty() {
+            println!("---");
+            println!("{}", "Cycles:".red());
+            for cycle in cycles {
+                let mut names: Vec<String> = cycle.iter().map(|&n| hierarchy.label(n)).collect();
+                names.push(names[0].clone());
+                println!("  {}", names.join(" -> "));
+            }
+        }
+        let multiple: Vec<usize> = (0..vault.notes.len())
+            .filter(|&n| hierarchy.parents[n].len() > 1)
+            .collect();
+        if !multiple.is_empty() {
+            println!("---");
+            println!("{}", "Notes with several parents:".yellow());
+            for n in multiple {
+                println!(
+                    "  {} <- {}",
+                    hierarchy.label(n),
+                    hierarchy.parents[n]
+                        .iter()
+                        .map(|&p| hierarchy.label(p))
+                        .collect::<Vec<String>>()
+                        .join(", ")
+                );
+            }
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,229 @@
+use crate::date;
+use crate::links::Link;
+use chrono::{NaiveDate, NaiveDateTime};
+use serde::{Serialize, Serializer};
+use std::collections::HashMap;
+use std::fmt::{Display, Formatter};
+
+/// Metadata of a note, keyed by field name.
+pub type Metadata = HashMap<String, Value>;
+
+/// A typed metadata value, from either frontmatter or an inline field.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    Text(String),
+    Number(f64),
+    Bool(bool),
+    Date(NaiveDate),
+    DateTime(NaiveDateTime),
+    List(Vec<Value>),
+    /// A `[[wikilink]]`
+    Link(Link),
+    Null,
+}
+
+impl Value {
+    /// Infer the type of a value written as text, e.g. after `key::`.
+    pub fn parse(value: &str) -> Self {
+        let value = value.trim();
+        if value.is_empty() {
+            return Self::Null;
+        }
+        if let Some(target) = value
+            .strip_prefix("[[")
+            .and_then(|v| v.strip_suffix("]]"))
+            .filter(|v| !v.contains("]]"))
+        {
+            return Self::Link(Link::wiki(target));
+        }
+        match value {
+            "true" => return Self::Bool(true),
+            "false" => return Self::Bool(false),
+            _ => {}
+        }
+        if let Ok(n) = value.parse::<f64>() {
+            if n.is_finite() {
+                return Self::Number(n);
+            }
+        }
+        if let Some(d) = date::parse_date(value) {
+            return Self::Date(d);
+        }
+        if let Some(dt) = date::parse_datetime(value) {
+            return Self::DateTime(dt);
+        }
+        Self::Text(value.into())
+    }
+
+    /// Add another value, turning this one into a list if needed.
+    pub fn push(&mut self, other: Value) {
+        match (self, other) {
+            (_, Value::Null) => {}
+            (this @ Value::Null, other) => *this = other,
+            (Value::List(items), Value::List(more)) => items.extend(more),
+            (Value::List(items), other) => items.push(other),
+            (this, other) => {
+                let first = std::mem::replace(this, Value::Null);
+                *this = Value::List(vec![first, other]);
+            }
+        }
+    }
+
+    /// The items of a list, or the value itself as a single item.
+    pub fn items(&self) -> Vec<&Value> {
+        match self {
+            Value::List(items) => items.iter().collect(),
+            Value::Null => Vec::new(),
+            other => vec![other],
+        }
+    }
+
+    pub fn type_name(&self) -> &'static str {
+        match self {
+            Value::Text(_) => "text",
+            Value::Number(_) => "number",
+            Value::Bool(_) => "bool",
+            Value::Date(_) => "date",
+            Value::DateTime(_) => "datetime",
+            Value::List(_) => "list",
+            Value::Link(_) => "link",
+            Value::Null => "null",
+        }
+    }
+}
+
+impl Display for Value {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Value::Text(s) => write!(f, "{}", s),
+            Value::Number(n) => write!(f, "{}", n),
+            Value::Bool(b) => write!(f, "{}", b),
+            Value::Date(d) => write!(f, "{}", d),
+            Value::DateTime(dt) => write!(f, "{}", dt.format("%Y-%m-%dT%H:%M:%S")),
+            Value::List(items) => write!(
+                f,
+                "{}",
+                items
+                    .iter()
+                    .map(|i| i.to_string())
+                    .collect::<Vec<String>>()
+                    .join(", ")
+            ),
+            Value::Link(link) => write!(f, "{}", link),
+            Value::Null => Ok(()),
+        }
+    }
+}
+
+/// Dates and links are written as text, the way they appear in a note.
+impl Serialize for Value {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self {
+            Value::Text(s) => serializer.serialize_str(s),
+            // Whole numbers are written without a fraction
+            Value::Number(n) if n.fract() == 0.0 && n.abs() < i64::MAX as f64 => {
+                serializer.serialize_i64(*n as i64)
+            }
+            Value::Number(n) => serializer.serialize_f64(*n),
+            Value::Bool(b) => serializer.serialize_bool(*b),
+            Value::List(items) => items.serialize(serializer),
+            Value::Null => serializer.serialize_unit(),
+            other => serializer.collect_str(other),
+        }
+    }
+}
+
+/// A metadata value that could not be converted to the requested type.
+#[derive(Debug)]
+pub struct ValueError {
+    pub expected: &'static str,
+    pub found: Box<Value>,
+}
+
+impl Display for ValueError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "expected {}, found {} `{}`",
+            self.expected,
+            self.found.type_name(),
+            self.found
+        )
+    }
+}
+
+impl std::error::Error for ValueError {}
+
+impl TryFrom<&Value> for NaiveDate {
+    type Error = ValueError;
+
+    fn try_from(value: &Value) -> Result<Self, Self::Error> {
+        match value {
+            Value::Date(d) => Ok(*d),
+            Value::DateTime(dt) => Ok(dt.date()),
+            // A repeated field keeps its first date
+            Value::List(items) if !items.is_empty() => NaiveDate::try_from(&items[0]),
+            other => Err(ValueError {
+                expected: "date",
+                found: Box::new(other.clone()),
+            }),
+        }
+    }
+}
+
+impl TryFrom<&Value> for String {
+    type Error = ValueError;
+
+    fn try_from(value: &Value) -> Result<Self, Self::Error> {
+        match value {
+            Value::Link(link) => Ok(link.target.clone()),
+            Value::List(_) => Err(ValueError {
+                expected: "text",
+                found: Box::new(value.clone()),
+            }),
+            other => Ok(other.to_string()),
+        }
+    }
+}
+
+impl TryFrom<&Value> for Vec<String> {
+    type Error = ValueError;
+
+    fn try_from(value: &Value) -> Result<Self, Self::Error> {
+        value.items().into_iter().map(String::try_from).collect()
+    }
+}
+
+impl TryFrom<&Value> for Vec<Link> {
+    type Error = ValueError;
+
+    fn try_from(value: &Value) -> Result<Self, Self::Error> {
+        value
+            .items()
+            .into_iter()
+            .map(|item| match item {
+                Value::Link(link) => Ok(link.clone()),
+                other => Err(ValueError {
+                    expected: "link",
+                    found: Box::new(other.clone()),
+                }),
+            })
+            .collect()
+    }
+}
+
+impl TryFrom<&Value> for usize {
+    type Error = ValueError;
+
+    fn try_from(value: &Value) -> Result<Self, Self::Error> {
+        match value {
+            Value::Number(n) if *n >= 0.0 && n.fract() == 0.0 => Ok(*n as usize),
+            Value::List(items) => Ok(items.len()),
+            Value::Null => Ok(0),
+            other => Err(ValueError {
+                expected: "count",
+                found: Box::new(other.clone()),
+            }),
+        }
+    }
+}
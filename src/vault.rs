@@ -0,0 +1,77 @@
+use std::collections::HashSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Recursive walk over an Obsidian vault.
+///
+/// Every file below `root` is returned as a path relative to `root`, sorted so
+/// that the order does not depend on what `read_dir` returns.
+/// Hidden folders such as `.obsidian` and `.trash` are never entered, and a
+/// folder reached again through a symlink is only read the first time.
+pub struct Walk<'a> {
+    root: &'a Path,
+    max_depth: Option<usize>,
+}
+
+impl<'a> Walk<'a> {
+    pub fn new(root: &'a Path) -> Self {
+        Self {
+            root,
+            max_depth: None,
+        }
+    }
+
+    /// Limit how many folders deep the walk goes. `0` only reads the vault root.
+    pub fn max_depth(mut self, depth: Option<usize>) -> Self {
+        self.max_depth = depth;
+        self
+    }
+
+    /// Every file, notes and attachments alike.
+    pub fn files(&self) -> Result<Vec<PathBuf>, std::io::Error> {
+        let mut found = Vec::new();
+        let mut visited = HashSet::new();
+        self.visit(self.root, 0, &mut visited, &mut found)?;
+        found.sort();
+        Ok(found)
+    }
+
+    fn visit(
+        &self,
+        dir: &Path,
+        depth: usize,
+        visited: &mut HashSet<PathBuf>,
+        found: &mut Vec<PathBuf>,
+    ) -> Result<(), std::io::Error> {
+        // Symlinks can point back up the tree, which would never end
+        if !visited.insert(fs::canonicalize(dir)?) {
+            return Ok(());
+        }
+        for entry in fs::read_dir(dir)? {
+            let path = entry?.path();
+            if is_hidden(&path) {
+                continue;
+            }
+            if path.is_dir() {
+                if self.max_depth.is_none_or(|max| depth < max) {
+                    self.visit(&path, depth + 1, visited, found)?;
+                }
+            } else if let Ok(relative) = path.strip_prefix(self.root) {
+                found.push(relative.to_path_buf());
+            }
+        }
+        Ok(())
+    }
+}
+
+fn is_hidden(path: &Path) -> bool {
+    path.file_name()
+        .and_then(|n| n.to_str())
+        .is_some_and(|n| n.starts_with('.'))
+}
+
+pub fn is_note(path: &Path) -> bool {
+    path.extension()
+        .and_then(|e| e.to_str())
+        .is_some_and(|e| e.eq_ignore_ascii_case("md"))
+}